@@ -51,13 +51,12 @@ function detect_path_name() {
 }
 
 
-function movie_ticket(token_id) {
+function movie_ticket() {
   window.contract.pay_and_mint_unsafe(
     {
       "nft_contract_id": nft_name,
       "template_id": "movie_tickets",
       "price": utils.format.parseNearAmount("1"),  // to be changed.
-      "token_id": token_id,
       "issued_at": Math.floor(Date.now() / 1000),
     },
     "30000000000000",  // 30 TGas
@@ -71,13 +70,12 @@ function movie_ticket(token_id) {
 }
 
 
-function zoo_ticket(token_id) {
+function zoo_ticket() {
   window.contract.pay_and_mint_unsafe(
     {
       "nft_contract_id": nft_name,
       "template_id": "entrance_tickets",
       "price": utils.format.parseNearAmount("3.5"),
-      "token_id": token_id,
       "issued_at": Math.floor(Date.now() / 1000),
     },
     "30000000000000",  // 30 TGas
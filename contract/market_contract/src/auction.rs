@@ -0,0 +1,341 @@
+use crate::*;
+
+/// struct for keeping track of auction conditions, passed in as part
+/// of SaleArgs. Times are in milliseconds. 
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionArgs {
+    pub reserve_price: U128,  // minimum first bid. 
+    pub min_bid_increment: U128,  // each bid must beat the highest by this much. 
+    pub duration: u64,  // how long auction runs from listing. 
+    pub extension_window: u64,  // bid within this window before end pushes end to now + window. 
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Bid {
+    pub bidder_id: AccountId,
+    pub amount: U128,
+    pub placed_at: u64,
+}
+
+/// English auction with NEAR bids held in escrow. Last bid is the highest.
+/// Only the latest MAX_BIDS_KEPT bids are kept. 
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Auction {
+    pub owner_id: AccountId,
+    pub approval_id: u64,
+    pub nft_contract_id: String,
+    pub token_id: String,
+    pub reserve_price: U128,
+    pub min_bid_increment: U128,
+    pub ends_at: u64,
+    pub extension_window: u64,
+    pub bids: Vec<Bid>,
+}
+
+// bids kept in an auction's history, so its storage stays bounded. 
+const MAX_BIDS_KEPT: usize = 10;
+
+impl AuctionArgs {
+    pub(crate) fn assert_valid(&self) {
+      require!(
+        self.min_bid_increment.0 > 0,
+        "Auction minimum bid increment must be more than 0."
+      );
+
+      require!(self.duration > 0, "Auction duration must be more than 0.");
+    }
+}
+
+
+#[near_bindgen]
+impl Contract {
+    /// Bid on an auction with the attached deposit. The previous highest
+    /// bidder is refunded. 
+    #[payable]
+    pub fn bid(
+      &mut self,
+      nft_contract_id: AccountId,
+      token_id: String,
+    ) {
+      let deposit = env::attached_deposit();
+      let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+
+      let mut auction = expect_lightweight(
+        self.auctions.get(&contract_and_token_id),
+        "No Auction"
+      );
+
+      let now = block_timestamp_ms();
+      require!(now < auction.ends_at, "Auction has ended.");
+
+      let bidder_id = env::predecessor_account_id();
+      require!(
+        auction.owner_id != bidder_id,
+        "Cannot bid on your own auction."
+      );
+
+      let min_bid = match auction.bids.last() {
+        Some(highest_bid) => highest_bid.amount.0 + auction.min_bid_increment.0,
+        None => auction.reserve_price.0
+      };
+
+      require!(
+        deposit >= min_bid,
+        format!(
+          "Bid too low: minimum bid is ~{} N, but only attached ~{} N",
+          yoctonear_to_near(min_bid),
+          yoctonear_to_near(deposit)
+        ),
+      );
+
+      // tickets can't be bid up past their resale cap. 
+      self.internal_assert_within_resale_cap(&contract_and_token_id, deposit, None);
+
+      // refund outbid bidder. 
+      if let Some(highest_bid) = auction.bids.last() {
+        Promise::new(highest_bid.bidder_id.clone()).transfer(highest_bid.amount.0);
+      }
+
+      auction.bids.push(Bid {
+        bidder_id,
+        amount: U128(deposit),
+        placed_at: now,
+      });
+
+      if auction.bids.len() > MAX_BIDS_KEPT {
+        auction.bids.remove(0);
+      }
+
+      // bids near the end extend the auction. 
+      if auction.ends_at - now < auction.extension_window {
+        auction.ends_at = now + auction.extension_window;
+      }
+
+      self.auctions.insert(&contract_and_token_id, &auction);
+    }
+
+    /// Settle an auction that has ended. Anyone can call this. Token goes to
+    /// the highest bidder and the bid is paid out with royalties. 
+    pub fn settle_auction(
+      &mut self,
+      nft_contract_id: AccountId,
+      token_id: String,
+    ) {
+      let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+
+      let auction = expect_lightweight(
+        self.auctions.get(&contract_and_token_id),
+        "No Auction"
+      );
+
+      require!(
+        block_timestamp_ms() >= auction.ends_at,
+        "Auction has not ended yet."
+      );
+
+      let auction = self.internal_remove_auction(nft_contract_id.clone(), token_id.clone());
+
+      // no bids, auction just ends. 
+      if let Some(highest_bid) = auction.bids.last() {
+        self.internal_transfer_payout(
+          nft_contract_id,
+          token_id,
+          auction.approval_id,
+          highest_bid.amount,
+          highest_bid.bidder_id.clone(),
+          None,
+        );
+      }
+    }
+
+    /// Owner removes an auction nobody has bid on yet. 
+    #[payable]
+    pub fn cancel_auction(
+      &mut self,
+      nft_contract_id: AccountId,
+      token_id: String,
+    ) {
+      assert_one_yocto();
+
+      let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+
+      let auction = expect_lightweight(
+        self.auctions.get(&contract_and_token_id),
+        "No Auction"
+      );
+
+      require!(
+        env::predecessor_account_id() == auction.owner_id,
+        "Only auction owner can cancel auction."
+      );
+
+      require!(
+        auction.bids.is_empty(),
+        "Cannot cancel an auction that already has bids."
+      );
+
+      self.internal_remove_auction(nft_contract_id, token_id);
+    }
+
+    // views
+    /// returns the number of auctions not yet settled. 
+    pub fn get_supply_auctions(&self) -> U64 {
+      U64(self.auctions.len())
+    }
+
+    /// returns the number of auctions for a given account. 
+    pub fn get_auction_supply_by_owner_id(&self, account_id: AccountId) -> U64 {
+      let by_owner_id = self.auctions_by_owner_id.get(&account_id);
+
+      if let Some(by_owner_id) = by_owner_id {
+        U64(by_owner_id.len())
+      } else {
+        U64(0)
+      }
+    }
+
+    /// returns paginated auctions still open for bids. 
+    pub fn get_active_auctions(
+      &self,
+      from_index: Option<U128>,
+      limit: Option<u64>,
+    ) -> Vec<Auction> {
+      let now = block_timestamp_ms();
+      let start = u128::from(from_index.unwrap_or(U128(0)));
+
+      self.auctions.values()
+          .filter(|auction| auction.ends_at > now)
+          .skip(start as usize)
+          .take(limit.unwrap_or(10) as usize)
+          .collect()
+    }
+
+    /// returns paginated auctions for a given account. 
+    pub fn get_auctions_by_owner_id(
+      &self,
+      account_id: AccountId,
+      from_index: Option<U128>,
+      limit: Option<u64>,
+    ) -> Vec<Auction> {
+      let by_owner_id = self.auctions_by_owner_id.get(&account_id);
+      let auctions = if let Some(by_owner_id) = by_owner_id {
+        by_owner_id
+      } else {
+        return vec![];
+      };
+
+      let start = u128::from(from_index.unwrap_or(U128(0)));
+
+      auctions.as_vector().iter()
+          .skip(start as usize)
+          .take(limit.unwrap_or(10) as usize)
+          .map(|contract_and_token_id| self.auctions.get(&contract_and_token_id).unwrap())
+          .collect()
+    }
+
+    /// get an auction for a given unique sale ID (contract + DELIMITER + token ID)
+    pub fn get_auction(&self, nft_contract_id: ContractAndTokenId) -> Option<Auction> {
+      self.auctions.get(&nft_contract_id)
+    }
+
+    /// get latest bids of an auction, oldest first. 
+    pub fn get_auction_bids(&self, nft_contract_id: ContractAndTokenId) -> Vec<Bid> {
+      self.auctions.get(&nft_contract_id)
+          .map(|auction| auction.bids)
+          .unwrap_or_default()
+    }
+}
+
+
+impl Contract {
+    /// Create an auction from nft_on_approve. Replaces any fixed price sale
+    /// of the token. 
+    pub(crate) fn internal_create_auction(
+      &mut self,
+      nft_contract_id: AccountId,
+      token_id: TokenId,
+      owner_id: AccountId,
+      approval_id: u64,
+      auction_args: AuctionArgs,
+    ) {
+      auction_args.assert_valid();
+
+      let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+
+      if let Some(auction) = self.auctions.get(&contract_and_token_id) {
+        require!(
+          auction.bids.is_empty(),
+          "Token is already on auction with bids."
+        );
+      }
+
+      if self.sales.get(&contract_and_token_id).is_some() {
+        self.internal_remove_sale(nft_contract_id.clone(), token_id.clone());
+      }
+
+      self.internal_assert_within_resale_cap(
+        &contract_and_token_id, 
+        auction_args.reserve_price.0, 
+        None
+      );
+
+      self.auctions.insert(
+        &contract_and_token_id,
+        &Auction {
+          owner_id: owner_id.clone(),
+          approval_id,
+          nft_contract_id: nft_contract_id.to_string(),
+          token_id,
+          reserve_price: auction_args.reserve_price,
+          min_bid_increment: auction_args.min_bid_increment,
+          ends_at: block_timestamp_ms() + auction_args.duration,
+          extension_window: auction_args.extension_window,
+          bids: vec![],
+        },
+      );
+
+      let mut by_owner_id = self.auctions_by_owner_id.get(&owner_id).unwrap_or_else(|| {
+        UnorderedSet::new(
+          StorageKey::AuctionsByOwnerIdInner {
+            account_id_hash: hash_account_id(&owner_id),
+          }
+          .try_to_vec()
+          .unwrap(),
+        )
+      });
+
+      by_owner_id.insert(&contract_and_token_id);
+      self.auctions_by_owner_id.insert(&owner_id, &by_owner_id);
+    }
+
+    /// remove an auction, returning the removed auction object. 
+    pub(crate) fn internal_remove_auction(
+      &mut self,
+      nft_contract_id: AccountId,
+      token_id: TokenId,
+    ) -> Auction {
+      let contract_and_token_id = format!("{}{}{}", &nft_contract_id, DELIMITER, token_id);
+
+      let auction = expect_lightweight(
+        self.auctions.remove(&contract_and_token_id),
+        "No Auction"
+      );
+
+      let mut by_owner_id = expect_lightweight(
+        self.auctions_by_owner_id.get(&auction.owner_id),
+        "No auction found by owner id."
+      );
+      by_owner_id.remove(&contract_and_token_id);
+
+      if by_owner_id.is_empty() {
+        self.auctions_by_owner_id.remove(&auction.owner_id);
+      } else {
+        self.auctions_by_owner_id.insert(&auction.owner_id, &by_owner_id);
+      }
+
+      auction
+    }
+}
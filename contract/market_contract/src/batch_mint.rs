@@ -0,0 +1,182 @@
+use crate::*;
+
+/// One token to mint in nft_batch_mint on the nft contract. 
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MintArgs {
+    pub token_id: TokenId,
+    pub metadata: TokenMetadata,
+    pub receiver_id: AccountId,
+    pub perpetual_royalties: Option<HashMap<AccountId, u16>>,
+    pub size: Option<usize>,
+    pub transfer_rules: Option<TransferRules>,
+    pub purchase_price: Option<U128>,
+}
+
+/// Token IDs minted by a batch purchase, and the deposit refunded above
+/// the price and storage reserve of every ticket. 
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchPurchase {
+    pub token_ids: Vec<TokenId>,
+    pub refund: U128,
+}
+
+
+#[near_bindgen]
+impl Contract {
+    /// Buy quantity tickets from a template in one call, e.g. for a school 
+    /// group. Attach at least quantity * (price + 0.1N). Tickets go to 
+    /// receivers, one each, or all to the payer if None. Token IDs are 
+    /// generated from the template ID and its sequence. Tickets are minted
+    /// with one nft_batch_mint: if minting fails, all of them are refunded. 
+    #[payable]
+    pub fn pay_and_mint_batch(
+      &mut self,
+      nft_contract_id: AccountId,
+      template_id: String,
+      quantity: u64,
+      price: U128,  // per ticket. 
+      receivers: Option<Vec<AccountId>>,
+      perpetual_royalties: Option<HashMap<AccountId, u16>>,
+      memo: Option<String>,
+      merkle_proof: Option<Vec<Base64VecU8>>,  // required during presale. 
+      promo_code: Option<String>,  // used once per ticket. 
+      tier: Option<String>,  // same for every ticket; required for tiered templates. 
+    ) -> BatchPurchase {
+      require!(
+        quantity > 0 && quantity <= MAX_BATCH_MINT,
+        format!("Can buy between 1 and {} tickets in one call.", MAX_BATCH_MINT)
+      );
+
+      let payer_id = env::predecessor_account_id();
+      let receivers = receivers.unwrap_or_else(|| vec![payer_id.clone(); quantity as usize]);
+
+      require!(
+        receivers.len() as u64 == quantity,
+        format!("Expected {} receivers, but found {}.", quantity, receivers.len())
+      );
+
+      let storage_for_mint = near_to_yoctonear(0.1);
+
+      require!(
+        env::attached_deposit() >= quantity as u128 * (price.0 + storage_for_mint),
+        format!(
+          "Attach at least ~{} N: {} tickets at price + 0.1N storage reserve each.",
+          yoctonear_to_near(quantity as u128 * (price.0 + storage_for_mint)),
+          quantity
+        )
+      );
+
+      // for tiered, presale and Dutch auction templates, price is the most you're willing to pay. 
+      let price = self.internal_purchase_price(
+        &template_id, &payer_id, merkle_proof.as_ref(), tier.as_ref(), promo_code.as_ref(), quantity, None, price
+      );
+
+      let refund = self.internal_refund_surplus(
+        quantity as u128 * (price.0 + storage_for_mint),
+        payer_id.clone()
+      );
+
+      let transfer_rules = self.transfer_rules.get(&template_id);
+
+      let mut token_ids: Vec<TokenId> = Vec::new();
+      let mut purchases_recorded: Vec<bool> = Vec::new();
+      let mut tokens: Vec<MintArgs> = Vec::new();
+      let mut protocol_fee = 0;
+
+      let promo_code_hash = promo_code.as_deref().map(hash_promo_code);
+      let initial_storage_usage = env::storage_usage();
+      for receiver_id in receivers {
+        let mint = self.internal_prepare_mint(
+          &nft_contract_id, &template_id, &payer_id, price, tier.as_ref(), promo_code_hash.as_ref(), None
+        );
+
+        protocol_fee = mint.protocol_fee;
+        purchases_recorded.push(mint.purchase_recorded);
+
+        tokens.push(MintArgs {
+          token_id: mint.token_id.clone(),
+          metadata: mint.metadata,
+          receiver_id,
+          perpetual_royalties: perpetual_royalties.clone(),
+          size: mint.size,
+          transfer_rules: transfer_rules.clone(),
+          purchase_price: Some(price),
+        });
+
+        token_ids.push(mint.token_id);
+      }
+
+      let mint_deposit = mint_deposit(quantity, initial_storage_usage);
+
+      // all tickets are minted in one call, so they succeed or fail together. 
+      ext_contract::nft_batch_mint(
+        tokens,
+        Some(payer_id.clone()),  // refund_to_signer
+        memo,
+
+        nft_contract_id.clone(),
+        mint_deposit,
+        Gas(GAS_FOR_MINTING.0 * quantity),
+      ).then(ext_self::resolve_mint_batch(
+        nft_contract_id,
+        template_id,
+        token_ids.clone(),
+        price,
+        payer_id,
+        purchases_recorded,
+        U128(protocol_fee),
+        tier,
+        promo_code_hash,
+        U128(mint_deposit),
+
+        env::current_account_id(),
+        NO_DEPOSIT,
+        GAS_FOR_RESOLVE_MINT_BATCH
+      ));
+
+      BatchPurchase {
+        token_ids,
+        refund: U128(refund),
+      }
+    }
+
+    /// Resolve promise when minting a batch. If minting failed, none of 
+    /// the tickets were minted: reverse every slot, give back promo code
+    /// uses and refund buyer the price and storage deposit of each. 
+    #[private]
+    pub fn resolve_mint_batch(
+      &mut self,
+      nft_contract_id: AccountId,
+      template_id: String,
+      token_ids: Vec<TokenId>,
+      price: U128,
+      buyer_id: AccountId,
+      purchases_recorded: Vec<bool>,
+      protocol_fee: U128,  // per ticket. 
+      tier: Option<String>,
+      promo_code_hash: Option<Base64VecU8>,  // used once per ticket. 
+      mint_deposit: U128,  // for all tickets. 
+    ) -> bool {
+      if is_promise_success() {
+        return true;
+      }
+
+      for (token_id, purchase_recorded) in token_ids.iter().zip(purchases_recorded) {
+        self.internal_revert_mint(
+          &nft_contract_id, token_id, &template_id, &buyer_id, None, price.0, protocol_fee.0, purchase_recorded
+        );
+        self.internal_revert_tier(&template_id, tier.as_ref());
+      }
+
+      self.internal_restore_promo_code(&template_id, promo_code_hash.as_ref(), token_ids.len() as u64);
+
+      // storage deposits attached to nft_mint come back to us on failure. 
+      Promise::new(buyer_id).transfer(
+        token_ids.len() as u128 * price.0 + mint_deposit.0
+      );
+
+      false
+    }
+}
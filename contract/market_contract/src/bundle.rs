@@ -0,0 +1,494 @@
+use crate::*;
+use near_sdk::PromiseResult;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BundleToken {
+    pub nft_contract_id: AccountId,
+    pub token_id: TokenId,
+    pub approval_id: Option<u64>,  // None until owner approves market for this bundle.
+}
+
+/// Several tokens sold together for one price in NEAR. Each token is
+/// approved with `bundle_id` in its SaleArgs before the bundle can be bought.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Bundle {
+    pub bundle_id: String,
+    pub owner_id: AccountId,
+    pub tokens: Vec<BundleToken>,
+    pub price: SalePriceInYoctoNear,
+    pub expires_at: Option<u64>,  // milliseconds, bundle can't be bought after.
+}
+
+/// Tokens of a bundle the market still holds, waiting to be delivered to
+/// receiver_id: the buyer, or the seller when a purchase is rolled back. 
+/// Seller and royalties are paid only once every token has arrived. 
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BundleDelivery {
+    pub owner_id: AccountId,
+    pub receiver_id: AccountId,
+    pub tokens: Vec<BundleToken>,  // not delivered yet. 
+    pub payouts: Vec<HashMap<AccountId, U128>>,  // empty on rollback. 
+    pub protocol_fee: U128,
+}
+
+impl Bundle {
+    pub(crate) fn is_expired(&self) -> bool {
+      match self.expires_at {
+        Some(expires_at) => block_timestamp_ms() >= expires_at,
+        None => false
+      }
+    }
+
+    /// price split evenly between the tokens, remainder on the first one.
+    /// Each token is paid out (and resale capped) with its share.
+    pub(crate) fn leg_prices(&self) -> Vec<Balance> {
+      let num_tokens = self.tokens.len() as u128;
+      let share = self.price.0 / num_tokens;
+      let remainder = self.price.0 - share * num_tokens;
+
+      (0..self.tokens.len())
+          .map(|i| if i == 0 { share + remainder } else { share })
+          .collect()
+    }
+}
+
+
+#[near_bindgen]
+impl Contract {
+    /// Create a bundle of tokens sold together. Takes up one storage slot.
+    /// Then approve the market for each token with `{"bundle_id": ...}` as msg.
+    pub fn create_bundle(
+      &mut self,
+      bundle_id: String,
+      tokens: Vec<(AccountId, TokenId)>,  // (nft_contract_id, token_id) pairs.
+      price: U128,
+      expires_at: Option<u64>,
+    ) {
+      let owner_id = env::predecessor_account_id();
+
+      require!(
+        self.bundles.get(&bundle_id).is_none() && self.bundle_deliveries.get(&bundle_id).is_none(),
+        "Bundle ID exists. Use another bundle_id."
+      );
+
+      require!(
+        tokens.len() >= 2 && tokens.len() <= MAX_BUNDLE_SIZE,
+        format!("A bundle must have between 2 and {} tokens.", MAX_BUNDLE_SIZE)
+      );
+
+      require!(price.0 > 0, "Bundle price must be larger than 0 yoctoNEAR.");
+
+      if let Some(expires_at) = expires_at {
+        require!(
+          expires_at > block_timestamp_ms(),
+          "Bundle expiry must be in the future."
+        );
+      }
+
+      self.internal_assert_storage_for_new_slot(&owner_id);
+
+      let mut bundle_tokens: Vec<BundleToken> = Vec::new();
+      for (nft_contract_id, token_id) in tokens {
+        require!(
+          !bundle_tokens.iter().any(|token|
+            token.nft_contract_id == nft_contract_id && token.token_id == token_id
+          ),
+          "Bundle has the same token more than once."
+        );
+
+        bundle_tokens.push(BundleToken { nft_contract_id, token_id, approval_id: None });
+      }
+
+      let bundle = Bundle {
+        bundle_id: bundle_id.clone(),
+        owner_id: owner_id.clone(),
+        tokens: bundle_tokens,
+        price,
+        expires_at,
+      };
+
+      // refuse bundles whose share of price exceeds a ticket's anti-scalping cap.
+      for (token, leg_price) in bundle.tokens.iter().zip(bundle.leg_prices()) {
+        let contract_and_token_id = format!(
+          "{}{}{}", token.nft_contract_id, DELIMITER, token.token_id
+        );
+        self.internal_assert_within_resale_cap(&contract_and_token_id, leg_price, None);
+      }
+
+      self.bundles.insert(&bundle_id, &bundle);
+
+      let mut by_owner_id = self.bundles_by_owner_id.get(&owner_id).unwrap_or_else(|| {
+        UnorderedSet::new(
+          StorageKey::BundlesByOwnerIdInner {
+            account_id_hash: hash_account_id(&owner_id),
+          }
+          .try_to_vec()
+          .unwrap(),
+        )
+      });
+
+      by_owner_id.insert(&bundle_id);
+      self.bundles_by_owner_id.insert(&owner_id, &by_owner_id);
+    }
+
+    /// removes a bundle from the market.
+    #[payable]
+    pub fn remove_bundle(&mut self, bundle_id: String) {
+      assert_one_yocto();
+      let bundle = self.internal_remove_bundle(&bundle_id);
+
+      require!(
+        env::predecessor_account_id() == bundle.owner_id,
+        "Only bundle owner can remove bundle."
+      );
+    }
+
+    /// Buy a bundle with the attached deposit. Every token is first moved
+    /// to the market; only if all succeed are they passed on to the buyer,
+    /// and the seller is paid once every token has arrived. Otherwise moved
+    /// tokens go back to the seller and the buyer is refunded. Deposit 
+    /// attached above the bundle price is refunded to the buyer.
+    #[payable]
+    pub fn buy_bundle(&mut self, bundle_id: String) -> Promise {
+      let deposit = env::attached_deposit();
+
+      let bundle = expect_lightweight(
+        self.bundles.get(&bundle_id),
+        "No Bundle"
+      );
+
+      let buyer_id = env::predecessor_account_id();
+      require!(
+        bundle.owner_id != buyer_id,
+        "Cannot buy your own bundle."
+      );
+
+      require!(!bundle.is_expired(), "Bundle has expired.");
+
+      require!(
+        bundle.tokens.iter().all(|token| token.approval_id.is_some()),
+        "Not all tokens in bundle have been approved yet."
+      );
+
+      require!(
+        deposit >= bundle.price.0,
+        format!(
+          "Insufficient price: You want to buy for ~{} N, but only attached ~{} N",
+          yoctonear_to_near(bundle.price.0),
+          yoctonear_to_near(deposit)
+        ),
+      );
+
+      self.internal_remove_bundle(&bundle_id);
+      self.internal_refund_surplus(bundle.price.0, buyer_id.clone());
+
+      let mut protocol_fee = 0;
+      let mut leg_amounts: Vec<U128> = Vec::new();
+      for leg_price in bundle.leg_prices() {
+        let leg_fee = self.internal_protocol_fee(leg_price, self.secondary_fee_bps);
+        protocol_fee += leg_fee;
+        leg_amounts.push(U128(leg_price - leg_fee));
+      }
+
+      // market holds every token until all of them are transferred.
+      let mut transfers: Option<Promise> = None;
+      for (token, leg_amount) in bundle.tokens.iter().zip(leg_amounts.iter()) {
+        let transfer_payout = ext_contract::nft_transfer_payout(
+          env::current_account_id(),
+          token.token_id.clone(),
+          token.approval_id.unwrap(),
+          "bundle payout from market".to_string(),  // memo
+          *leg_amount,
+          10,  // max amount of accounts market can payout
+          token.nft_contract_id.clone(),
+          1,  // attached yoctoNEAR
+          GAS_FOR_NFT_TRANSFER,
+        );
+
+        transfers = Some(match transfers {
+          Some(transfers) => transfers.and(transfer_payout),
+          None => transfer_payout,
+        });
+      }
+
+      let num_tokens = bundle.tokens.len() as u64;
+
+      transfers.unwrap().then(ext_self::resolve_bundle_purchase(
+        bundle_id,
+        bundle.owner_id,
+        bundle.tokens,
+        leg_amounts,
+        buyer_id,
+        bundle.price,
+        U128(protocol_fee),
+        env::current_account_id(),
+        NO_DEPOSIT,
+        Gas(
+          GAS_FOR_RESOLVE_BUNDLE_PURCHASE.0 
+            + GAS_FOR_NFT_TRANSFER.0 * num_tokens 
+            + GAS_FOR_RESOLVE_BUNDLE_DELIVERY.0
+        ),
+      ))
+    }
+
+    /// Resolve promise when calling nft_transfer_payout on every token of a
+    /// bundle. Returns amount refunded to buyer.
+    #[private]
+    pub fn resolve_bundle_purchase(
+      &mut self,
+      bundle_id: String,
+      owner_id: AccountId,
+      tokens: Vec<BundleToken>,
+      leg_amounts: Vec<U128>,
+      buyer_id: AccountId,
+      price: U128,
+      protocol_fee: U128,
+    ) -> U128 {
+      let mut payouts: Vec<HashMap<AccountId, U128>> = Vec::new();
+      let mut held_tokens: Vec<&BundleToken> = Vec::new();  // now owned by market.
+
+      for (i, (token, leg_amount)) in tokens.iter().zip(leg_amounts.iter()).enumerate() {
+        if let PromiseResult::Successful(value) = env::promise_result(i as u64) {
+          held_tokens.push(token);
+
+          if let Some(payout) = payout_from_value(&value, leg_amount.0) {
+            payouts.push(payout);
+          }
+        }
+      }
+
+      // roll back: return tokens already moved and refund buyer.
+      if payouts.len() != tokens.len() {
+        env::log_str(&format!("Bundle {} failed, refunding buyer.", bundle_id));
+
+        Promise::new(buyer_id).transfer(price.0);
+
+        if !held_tokens.is_empty() {
+          self.internal_deliver_bundle(bundle_id, BundleDelivery {
+            owner_id: owner_id.clone(),
+            receiver_id: owner_id,
+            tokens: held_tokens.into_iter().cloned().collect(),
+            payouts: vec![],
+            protocol_fee: U128(0),
+          });
+        }
+
+        return price;
+      }
+
+      self.internal_deliver_bundle(bundle_id, BundleDelivery {
+        owner_id,
+        receiver_id: buyer_id,
+        tokens: held_tokens.into_iter().cloned().collect(),
+        payouts,
+        protocol_fee,
+      });
+
+      U128(0)
+    }
+
+    /// Resolve promise when delivering held bundle tokens. Once every token
+    /// has arrived, pay out the seller, royalties and protocol fee. Tokens
+    /// that didn't arrive stay held, to be sent again with 
+    /// `retry_bundle_delivery`. Returns whether delivery is complete. 
+    #[private]
+    pub fn resolve_bundle_delivery(
+      &mut self,
+      bundle_id: String,
+      delivery: BundleDelivery,
+    ) -> bool {
+      let mut delivery = delivery;
+      let undelivered: Vec<BundleToken> = delivery.tokens.iter()
+          .enumerate()
+          .filter(|(i, _)| !matches!(env::promise_result(*i as u64), PromiseResult::Successful(_)))
+          .map(|(_, token)| token.clone())
+          .collect();
+
+      if !undelivered.is_empty() {
+        env::log_str(&format!(
+          "Bundle {}: {} tokens not delivered to {}, call retry_bundle_delivery.",
+          bundle_id,
+          undelivered.len(),
+          delivery.receiver_id
+        ));
+
+        delivery.tokens = undelivered;
+        self.bundle_deliveries.insert(&bundle_id, &delivery);
+        return false;
+      }
+
+      self.internal_credit_treasury(None, delivery.protocol_fee.0);
+
+      for payout in delivery.payouts {
+        for (receiver_id, amount) in payout {
+          Promise::new(receiver_id).transfer(amount.0);
+        }
+      }
+
+      true
+    }
+
+    /// Send held bundle tokens to their receiver again, e.g. after a
+    /// transfer lock has ended. Anyone can call this. 
+    pub fn retry_bundle_delivery(&mut self, bundle_id: String) -> Promise {
+      let delivery = expect_lightweight(
+        self.bundle_deliveries.remove(&bundle_id),
+        "No undelivered tokens for this bundle."
+      );
+
+      self.internal_deliver_bundle(bundle_id, delivery)
+    }
+
+    // views
+
+    /// returns the number of bundles for a given account.
+    pub fn get_bundle_supply_by_owner_id(&self, account_id: AccountId) -> U64 {
+      let by_owner_id = self.bundles_by_owner_id.get(&account_id);
+
+      if let Some(by_owner_id) = by_owner_id {
+        U64(by_owner_id.len())
+      } else {
+        U64(0)
+      }
+    }
+
+    /// returns paginated bundles that can still be bought.
+    pub fn get_bundles(
+      &self,
+      from_index: Option<U128>,
+      limit: Option<u64>,
+    ) -> Vec<Bundle> {
+      let start = u128::from(from_index.unwrap_or(U128(0)));
+
+      self.bundles.values()
+          .filter(|bundle| !bundle.is_expired())
+          .skip(start as usize)
+          .take(limit.unwrap_or(10) as usize)
+          .collect()
+    }
+
+    /// returns paginated bundles for a given account, hiding expired ones.
+    pub fn get_bundles_by_owner_id(
+      &self,
+      account_id: AccountId,
+      from_index: Option<U128>,
+      limit: Option<u64>,
+    ) -> Vec<Bundle> {
+      let by_owner_id = self.bundles_by_owner_id.get(&account_id);
+      let bundles = if let Some(by_owner_id) = by_owner_id {
+        by_owner_id
+      } else {
+        return vec![];
+      };
+
+      let start = u128::from(from_index.unwrap_or(U128(0)));
+
+      bundles.as_vector().iter()
+          .map(|bundle_id| self.bundles.get(&bundle_id).unwrap())
+          .filter(|bundle| !bundle.is_expired())
+          .skip(start as usize)
+          .take(limit.unwrap_or(10) as usize)
+          .collect()
+    }
+
+    /// get a bundle for a given bundle ID. None if expired.
+    pub fn get_bundle(&self, bundle_id: String) -> Option<Bundle> {
+      self.bundles.get(&bundle_id).filter(|bundle| !bundle.is_expired())
+    }
+
+    /// get tokens of a bought bundle still held by the market. 
+    pub fn get_bundle_delivery(&self, bundle_id: String) -> Option<BundleDelivery> {
+      self.bundle_deliveries.get(&bundle_id)
+    }
+}
+
+
+impl Contract {
+    /// Record market's approval for a bundle token, from nft_on_approve.
+    pub(crate) fn internal_approve_bundle_token(
+      &mut self,
+      bundle_id: String,
+      nft_contract_id: AccountId,
+      token_id: TokenId,
+      owner_id: AccountId,
+      approval_id: u64,
+    ) {
+      let mut bundle = expect_lightweight(
+        self.bundles.get(&bundle_id),
+        "No Bundle"
+      );
+
+      require!(
+        bundle.owner_id == owner_id,
+        "Only bundle owner can approve tokens for it."
+      );
+
+      let token = expect_lightweight(
+        bundle.tokens.iter_mut().find(|token|
+          token.nft_contract_id == nft_contract_id && token.token_id == token_id
+        ),
+        "Token is not part of this bundle."
+      );
+
+      token.approval_id = Some(approval_id);
+      self.bundles.insert(&bundle_id, &bundle);
+    }
+
+    /// Transfer held bundle tokens to the delivery's receiver, then resolve. 
+    pub(crate) fn internal_deliver_bundle(
+      &mut self,
+      bundle_id: String,
+      delivery: BundleDelivery,
+    ) -> Promise {
+      let mut transfers: Option<Promise> = None;
+      for token in delivery.tokens.iter() {
+        let transfer = ext_contract::nft_transfer(
+          delivery.receiver_id.clone(),
+          token.token_id.clone(),
+          0,  // market is owner, approval not checked.
+          Some(format!("bundle {}", bundle_id)),
+          token.nft_contract_id.clone(),
+          1,
+          GAS_FOR_NFT_TRANSFER,
+        );
+
+        transfers = Some(match transfers {
+          Some(transfers) => transfers.and(transfer),
+          None => transfer,
+        });
+      }
+
+      expect_lightweight(transfers, "No tokens to deliver.").then(ext_self::resolve_bundle_delivery(
+        bundle_id,
+        delivery,
+        env::current_account_id(),
+        NO_DEPOSIT,
+        GAS_FOR_RESOLVE_BUNDLE_DELIVERY,
+      ))
+    }
+
+    /// remove bundle from the market and its owner's index.
+    pub(crate) fn internal_remove_bundle(&mut self, bundle_id: &String) -> Bundle {
+      let bundle = expect_lightweight(
+        self.bundles.remove(bundle_id),
+        "No Bundle"
+      );
+
+      let mut by_owner_id = expect_lightweight(
+        self.bundles_by_owner_id.get(&bundle.owner_id),
+        "No bundle found by owner id."
+      );
+      by_owner_id.remove(bundle_id);
+
+      if by_owner_id.is_empty() {
+        self.bundles_by_owner_id.remove(&bundle.owner_id);
+      } else {
+        self.bundles_by_owner_id.insert(&bundle.owner_id, &by_owner_id);
+      }
+
+      bundle
+    }
+}
@@ -0,0 +1,164 @@
+use crate::*;
+
+/// Descending price for minting from a template. Price starts at 
+/// start_price and drops in equal steps every price_drop_interval until 
+/// it reaches floor_price at starts_at + duration. Times are in milliseconds. 
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DutchAuction {
+    pub start_price: U128,
+    pub floor_price: U128,
+    pub starts_at: u64,
+    pub duration: u64,
+    pub price_drop_interval: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PricePoint {
+    pub at: u64,
+    pub price: U128,
+}
+
+/// Returned from `get_dutch_auction_schedule` view call. 
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DutchAuctionSchedule {
+    pub dutch_auction: DutchAuction,
+    pub current_price: U128,
+    pub schedule: Vec<PricePoint>,
+}
+
+// max number of price drops, so the schedule stays viewable. 
+const MAX_PRICE_DROPS: u64 = 100;
+
+impl DutchAuction {
+    pub(crate) fn assert_valid(&self) {
+      require!(
+        self.start_price.0 >= self.floor_price.0,
+        "Dutch auction start price must be at least the floor price."
+      );
+
+      require!(
+        self.price_drop_interval > 0 && self.duration >= self.price_drop_interval,
+        "Dutch auction duration must be at least one price drop interval."
+      );
+
+      require!(
+        self.duration / self.price_drop_interval <= MAX_PRICE_DROPS,
+        format!("Dutch auction cannot have more than {} price drops.", MAX_PRICE_DROPS)
+      );
+    }
+
+    /// price after a given number of drops. 
+    fn price_after_drops(&self, drops: u64) -> Balance {
+      let total_drops = self.duration / self.price_drop_interval;
+      let drops = drops.min(total_drops);
+
+      self.start_price.0 
+        - (self.start_price.0 - self.floor_price.0) * drops as u128 / total_drops as u128
+    }
+
+    /// price at a given time in milliseconds. 
+    pub(crate) fn price_at(&self, timestamp: u64) -> Balance {
+      let elapsed = timestamp.saturating_sub(self.starts_at);
+      self.price_after_drops(elapsed / self.price_drop_interval)
+    }
+}
+
+
+#[near_bindgen]
+impl Contract {
+    // views
+    /// returns the current price of a Dutch auction template, None if the
+    /// template isn't priced by Dutch auction. 
+    pub fn get_template_price(&self, template_id: String) -> Option<U128> {
+      self.template_dutch_auction.get(&template_id)
+          .map(|dutch_auction| U128(dutch_auction.price_at(block_timestamp_ms())))
+    }
+
+    /// returns the Dutch auction of a template with its current price and 
+    /// every price drop. 
+    pub fn get_dutch_auction_schedule(&self, template_id: String) -> Option<DutchAuctionSchedule> {
+      let dutch_auction = self.template_dutch_auction.get(&template_id)?;
+      let total_drops = dutch_auction.duration / dutch_auction.price_drop_interval;
+
+      let schedule = (0..=total_drops)
+          .map(|drops| PricePoint {
+            at: dutch_auction.starts_at + drops * dutch_auction.price_drop_interval,
+            price: U128(dutch_auction.price_after_drops(drops)),
+          })
+          .collect();
+
+      Some(DutchAuctionSchedule {
+        current_price: U128(dutch_auction.price_at(block_timestamp_ms())),
+        dutch_auction,
+        schedule,
+      })
+    }
+}
+
+
+impl Contract {
+    /// Price charged for minting from a template. For tiered templates this
+    /// is the price of the tier bought. Otherwise during a presale this is
+    /// the presale price if set, and for Dutch auction templates the current
+    /// price. Either way it can't be above max_price the buyer is willing to
+    /// pay. Other templates charge max_price. During a presale buyer_id must
+    /// be allowlisted. 
+    pub(crate) fn internal_charge_price(
+      &self,
+      template_id: &String,
+      buyer_id: &AccountId,
+      merkle_proof: Option<&Vec<Base64VecU8>>,
+      price_tier: Option<&PriceTier>,
+      max_price: U128,
+    ) -> U128 {
+      let presale_price = self.internal_assert_presale_access(template_id, buyer_id, merkle_proof);
+
+      if let Some(price_tier) = price_tier {
+        require!(
+          max_price.0 >= price_tier.price.0,
+          format!(
+            "Tier price is ~{} N, more than the ~{} N you're willing to pay.",
+            yoctonear_to_near(price_tier.price.0),
+            yoctonear_to_near(max_price.0)
+          )
+        );
+
+        return price_tier.price;
+      }
+
+      if let Some(presale_price) = presale_price {
+        require!(
+          max_price.0 >= presale_price.0,
+          format!(
+            "Presale price is ~{} N, more than the ~{} N you're willing to pay.",
+            yoctonear_to_near(presale_price.0),
+            yoctonear_to_near(max_price.0)
+          )
+        );
+
+        return presale_price;
+      }
+
+      let dutch_auction = if let Some(dutch_auction) = self.template_dutch_auction.get(template_id) {
+        dutch_auction
+      } else {
+        return max_price;
+      };
+
+      let current_price = dutch_auction.price_at(block_timestamp_ms());
+
+      require!(
+        max_price.0 >= current_price,
+        format!(
+          "Current price is ~{} N, more than the ~{} N you're willing to pay.",
+          yoctonear_to_near(current_price),
+          yoctonear_to_near(max_price.0)
+        )
+      );
+
+      U128(current_price)
+    }
+}
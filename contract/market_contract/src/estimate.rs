@@ -0,0 +1,93 @@
+use crate::*;
+
+// every storage record costs its key and value plus this many bytes.
+const STORAGE_BYTES_PER_RECORD: u64 = 40;
+
+// attached to nft_mint for the nft contract's storage, excess refunded. 
+const STORAGE_FOR_MINT: f64 = 0.1;
+
+
+/// bytes taken up by a LookupMap entry with the given prefix, key and value.
+fn entry_bytes<K: BorshSerialize, V: BorshSerialize>(prefix: StorageKey, key: &K, value: &V) -> u64 {
+  (prefix.try_to_vec().unwrap().len()
+    + key.try_to_vec().unwrap().len()
+    + value.try_to_vec().unwrap().len()) as u64
+    + STORAGE_BYTES_PER_RECORD
+}
+
+
+#[near_bindgen]
+impl Contract {
+    /// Exact deposit generate_template needs for the given arguments. 
+    pub fn estimate_generate_template_deposit(
+      &self,
+      template_owner: AccountId,
+      template_id: String,
+      max_num_of_mint: u64,
+      metadata: TokenMetadata,
+      size: Option<usize>,
+      transfer_rules: Option<TransferRules>,
+      dutch_auction: Option<DutchAuction>,
+      max_per_account: Option<u64>,
+    ) -> U128 {
+      let mut bytes = entry_bytes(StorageKey::TemplateOwner, &template_id, &template_owner)
+        + entry_bytes(StorageKey::TokenTemplates, &template_id, &metadata)
+        + entry_bytes(StorageKey::TemplateSize, &template_id, &max_num_of_mint);
+
+      if let Some(size) = size {
+        bytes += entry_bytes(StorageKey::NFTSize, &template_id, &size);
+      }
+
+      if let Some(transfer_rules) = transfer_rules {
+        bytes += entry_bytes(StorageKey::TransferRules, &template_id, &transfer_rules);
+      }
+
+      if let Some(dutch_auction) = dutch_auction {
+        bytes += entry_bytes(StorageKey::TemplateDutchAuction, &template_id, &dutch_auction);
+      }
+
+      if let Some(max_per_account) = max_per_account {
+        bytes += entry_bytes(StorageKey::TemplateMaxPerAccount, &template_id, &max_per_account);
+      }
+
+      U128(Balance::from(bytes) * env::storage_byte_cost())
+    }
+
+    /// Exact deposit pay_and_mint and pay_and_mint_unsafe need: the price
+    /// (presale price during a priced presale, current price for Dutch 
+    /// auction templates, tier price for tiered templates), less the promo
+    /// code discount if given, plus storage for minting.
+    pub fn estimate_pay_and_mint_deposit(
+      &self,
+      template_id: String,
+      price: Option<U128>,
+      promo_code: Option<String>,
+      tier: Option<String>,
+    ) -> U128 {
+      let price = self.internal_price_tier(&template_id, tier.as_ref())
+          .map(|price_tier| price_tier.price)
+          .or_else(|| self.internal_presale_price(&template_id))
+          .or_else(|| self.get_template_price(template_id.clone()))
+          .or(price)
+          .unwrap_or_else(|| env::panic_str("Template has no set price. Pass in the price you pay."));
+
+      let price = match promo_code {
+        Some(code) => self.internal_promo_price(&template_id, &code, price),
+        None => price,
+      };
+
+      U128(price.0 + near_to_yoctonear(STORAGE_FOR_MINT))
+    }
+
+    /// Deposit account_id needs before it can list a sale, auction or bundle,
+    /// or place an offer: zero if its storage balance already covers one 
+    /// more slot. 
+    pub fn estimate_storage_deposit(&self, account_id: AccountId) -> U128 {
+      let paid_storage = self.storage_deposits.get(&account_id).unwrap_or(0);
+      let storage_required = (
+        self.internal_storage_slots_used(&account_id) + 1
+      ) as u128 * STORAGE_PER_SALE;
+
+      U128(storage_required.saturating_sub(paid_storage))
+    }
+}
@@ -17,6 +17,14 @@ trait ExtContract {
       max_len_payout: u32,  // max amount of accounts market can payout at once. 
     );
 
+    fn nft_transfer(
+      &mut self,
+      receiver_id: AccountId,
+      token_id: TokenId,
+      approval_id: u64,
+      memo: Option<String>,
+    );
+
     fn nft_mint(
       &mut self,
       token_id: TokenId,
@@ -25,5 +33,40 @@ trait ExtContract {
       perpetual_royalties: Option<HashMap<AccountId, u16>>,
       size: Option<usize>,
       refund_to_signer: Option<AccountId>,
+      transfer_rules: Option<TransferRules>,
+      purchase_price: Option<U128>,
+      memo: Option<String>,
+    );
+
+    fn nft_batch_mint(
+      &mut self,
+      tokens: Vec<MintArgs>,
+      refund_to_signer: Option<AccountId>,
+      memo: Option<String>,
+    ) -> Vec<TokenId>;
+
+    fn nft_is_approved(
+      &self,
+      token_id: TokenId,
+      approved_account_id: AccountId,
+      approval_id: Option<u64>,
+    ) -> bool;
+
+    fn nft_burn(
+      &mut self,
+      token_id: TokenId,
+      owner_id: Option<AccountId>,  // token must still belong to this account, if given. 
+    ) -> AccountId;
+}
+
+/// Cross contract calls to whitelisted fungible token contracts,
+/// used to pay out purchases made in fungible tokens. 
+#[ext_contract(ext_ft)]
+trait ExtFungibleToken {
+    fn ft_transfer(
+      &mut self,
+      receiver_id: AccountId,
+      amount: U128,
+      memo: Option<String>,
     );
-}
\ No newline at end of file
+}
@@ -0,0 +1,365 @@
+use crate::*;
+use near_sdk::PromiseOrValue;
+
+// Fungible token callbacks, for paying with whitelisted fungible tokens. 
+
+/// struct passed in as `msg` of `ft_transfer_call`. Buys the sale of the
+/// given token; or if template_id is passed in, mints a new token from the
+/// template, its ID generated like in pay_and_mint. 
+/// Minted tokens can be gifted to receiver_id, with a memo for the mint event. 
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtPurchaseArgs {
+    pub nft_contract_id: AccountId,
+    pub token_id: Option<TokenId>,  // required to buy a sale. 
+    pub template_id: Option<String>,
+    pub perpetual_royalties: Option<HashMap<AccountId, u16>>,
+    pub receiver_id: Option<AccountId>,
+    pub memo: Option<String>,
+    pub merkle_proof: Option<Vec<Base64VecU8>>,  // required during presale. 
+    pub promo_code: Option<String>,
+}
+
+
+/// when ft_transfer_call is called on a fungible token contract, it'll fire
+/// a cross contract call to this marketplace and this is the function that
+/// is invoked. Returns the amount of unused tokens, refunded to the sender. 
+trait FungibleTokenReceiver {
+    fn ft_on_transfer(
+      &mut self,
+      sender_id: AccountId,
+      amount: U128,
+      msg: String,
+    ) -> PromiseOrValue<U128>;
+}
+
+
+#[near_bindgen]
+impl FungibleTokenReceiver for Contract {
+
+    fn ft_on_transfer(
+      &mut self,
+      sender_id: AccountId,
+      amount: U128,
+      msg: String,
+    ) -> PromiseOrValue<U128> {
+      let ft_token_id = env::predecessor_account_id();
+
+      require!(
+        self.ft_token_ids.contains(&ft_token_id),
+        format!("Fungible token {} is not accepted on this marketplace.", ft_token_id)
+      );
+
+      let FtPurchaseArgs { 
+        nft_contract_id, token_id, template_id, perpetual_royalties, receiver_id, memo, merkle_proof, promo_code 
+      } = 
+          near_sdk::serde_json::from_str(&msg).unwrap_or_else(|_|
+            env::panic_str("Message passed in is not valid FtPurchaseArgs")
+      );
+
+      if let Some(template_id) = template_id {
+        return PromiseOrValue::Promise(self.internal_ft_mint(
+          nft_contract_id,
+          template_id,
+          perpetual_royalties,
+          sender_id,
+          receiver_id,
+          memo,
+          merkle_proof,
+          promo_code,
+          ft_token_id,
+          amount,
+        ));
+      }
+
+      let token_id = expect_lightweight(token_id, "Pass in token_id of the sale to buy.");
+      let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+
+      let sale = expect_lightweight(
+        self.sales.get(&contract_and_token_id),
+        "No Sale"
+      );
+
+      require!(
+        sale.owner_id != sender_id,
+        "Cannot bid on your own sale."
+      );
+
+      require!(!sale.is_expired(), "Sale has expired.");
+
+      let price = expect_lightweight(
+        sale.ft_sale_conditions.get(&ft_token_id),
+        "This sale is not for sale in this fungible token."
+      ).0;
+
+      require!(
+        amount.0 >= price,
+        format!(
+          "Insufficient price: You want to buy for {}, but only transferred {}",
+          price,
+          amount.0
+        ),
+      );
+
+      PromiseOrValue::Promise(self.process_purchase(
+        nft_contract_id,
+        token_id,
+        amount,
+        sender_id,
+        Some(ft_token_id),
+      ))
+    }
+}
+
+
+#[near_bindgen]
+impl Contract {
+    /// Whitelist fungible tokens accepted as payment. Contract owner only. 
+    pub fn add_ft_token_ids(&mut self, ft_token_ids: Vec<FungibleTokenId>) {
+      require!(
+        env::predecessor_account_id() == self.owner_id,
+        "Only contract owner can whitelist fungible tokens."
+      );
+
+      for ft_token_id in ft_token_ids {
+        self.ft_token_ids.insert(&ft_token_id);
+      }
+    }
+
+    /// Remove fungible tokens from whitelist. Contract owner only. 
+    pub fn remove_ft_token_ids(&mut self, ft_token_ids: Vec<FungibleTokenId>) {
+      require!(
+        env::predecessor_account_id() == self.owner_id,
+        "Only contract owner can remove fungible tokens."
+      );
+
+      for ft_token_id in ft_token_ids {
+        self.ft_token_ids.remove(&ft_token_id);
+      }
+    }
+
+    /// Set template prices in whitelisted fungible tokens. Template owner only.
+    /// Replaces prices previously set. 
+    #[payable]
+    pub fn set_template_ft_prices(
+      &mut self, 
+      template_id: String, 
+      ft_prices: HashMap<FungibleTokenId, U128>
+    ) {
+      let initial_storage_usage = env::storage_usage();
+
+      self.internal_assert_template_owner(&template_id);
+
+      for ft_token_id in ft_prices.keys() {
+        require!(
+          self.ft_token_ids.contains(ft_token_id),
+          format!("Fungible token {} is not accepted on this marketplace.", ft_token_id)
+        );
+      }
+
+      self.template_ft_prices.insert(&template_id, &ft_prices);
+
+      let required_storage_in_bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+      self.refund_deposit(required_storage_in_bytes, env::predecessor_account_id());
+    }
+
+    /// Claim a fungible token payment that couldn't be transferred before,
+    /// e.g. after registering on the token contract. 
+    #[payable]
+    pub fn claim_ft_payment(&mut self, ft_token_id: FungibleTokenId) -> U128 {
+      assert_one_yocto();
+
+      let receiver_id = env::predecessor_account_id();
+      let amount = expect_lightweight(
+        self.unclaimed_ft_payments.remove(&(receiver_id.clone(), ft_token_id.clone())),
+        "No payment to claim in this fungible token."
+      );
+
+      self.internal_pay(receiver_id, Some(ft_token_id), amount);
+
+      U128(amount)
+    }
+
+    // views
+    /// returns whitelisted fungible tokens. 
+    pub fn get_ft_token_ids(&self) -> Vec<FungibleTokenId> {
+      self.ft_token_ids.to_vec()
+    }
+
+    /// returns fungible token payment an account can claim. 
+    pub fn get_unclaimed_ft_payment(&self, account_id: AccountId, ft_token_id: FungibleTokenId) -> U128 {
+      U128(self.unclaimed_ft_payments.get(&(account_id, ft_token_id)).unwrap_or(0))
+    }
+
+    /// returns template prices in fungible tokens. 
+    pub fn get_template_ft_prices(&self, template_id: String) -> HashMap<FungibleTokenId, U128> {
+      self.template_ft_prices.get(&template_id).unwrap_or_default()
+    }
+
+    // ================ PRIVATE FUNCTIONS ======================= //
+
+    /// Resolve promise when calling nft_transfer_payout for a purchase in
+    /// fungible tokens. Returns amount the fungible token contract should 
+    /// refund the buyer. 
+    #[private]
+    pub fn resolve_ft_purchase(
+      &mut self,
+      buyer_id: AccountId,
+      ft_token_id: FungibleTokenId,
+      price: U128,
+      protocol_fee: U128,
+    ) -> U128 {
+      let payout = if let Some(payout) = payout_from_promise_result(price.0 - protocol_fee.0) {
+        payout
+      } else {
+        env::log_str(&format!("Refunding {} to @{}", price.0, buyer_id));
+        return price;  // everything is unused, refunded by fungible token contract. 
+      };
+
+      self.internal_credit_treasury(Some(&ft_token_id), protocol_fee.0);
+
+      // fungible token payouts. Failed ones are credited for receivers to claim. 
+      for (receiver_id, amount) in payout {
+        self.internal_pay(receiver_id, Some(ft_token_id.clone()), amount.0);
+      }
+
+      U128(0)
+    }
+
+    /// Resolve promise when transferring a fungible token payment. If the 
+    /// transfer failed, credit the amount for the receiver to claim. 
+    #[private]
+    pub fn resolve_ft_payment(
+      &mut self,
+      receiver_id: AccountId,
+      ft_token_id: FungibleTokenId,
+      amount: U128,
+    ) -> bool {
+      if is_promise_success() {
+        return true;
+      }
+
+      let key = (receiver_id, ft_token_id);
+      let unclaimed = self.unclaimed_ft_payments.get(&key).unwrap_or(0);
+      self.unclaimed_ft_payments.insert(&key, &(unclaimed + amount.0));
+
+      false
+    }
+
+    /// Resolve promise when calling nft_mint for a mint paid in fungible 
+    /// tokens. Returns amount the fungible token contract should refund 
+    /// the buyer. 
+    #[private]
+    pub fn resolve_ft_mint(
+      &mut self,
+      nft_contract_id: AccountId,
+      template_id: String,
+      token_id: TokenId,
+      buyer_id: AccountId,
+      ft_token_id: FungibleTokenId,
+      amount: U128,
+      price: U128,
+      purchase_recorded: bool,
+      protocol_fee: U128,
+      promo_code_hash: Option<Base64VecU8>,
+      mint_deposit: U128,
+    ) -> U128 {
+      if is_promise_success() {
+        return U128(amount.0 - price.0);
+      }
+
+      self.internal_revert_mint(
+        &nft_contract_id, 
+        &token_id, 
+        &template_id, 
+        &buyer_id, 
+        Some(&ft_token_id), 
+        price.0, 
+        protocol_fee.0, 
+        purchase_recorded
+      );
+      self.internal_restore_promo_code(&template_id, promo_code_hash.as_ref(), 1);
+
+      // storage deposit attached to nft_mint comes back to us on failure. 
+      let balance = self.storage_deposits.get(&buyer_id).unwrap_or(0);
+      self.storage_deposits.insert(&buyer_id, &(balance + mint_deposit.0));
+
+      amount
+    }
+}
+
+
+impl Contract {
+    /// Mint from a template paid in fungible tokens. Storage for the minted
+    /// token is paid from the buyer's storage deposit on the marketplace. 
+    pub(crate) fn internal_ft_mint(
+      &mut self,
+      nft_contract_id: AccountId,
+      template_id: String,
+      perpetual_royalties: Option<HashMap<AccountId, u16>>,
+      buyer_id: AccountId,
+      receiver_id: Option<AccountId>,
+      memo: Option<String>,
+      merkle_proof: Option<Vec<Base64VecU8>>,
+      promo_code: Option<String>,
+      ft_token_id: FungibleTokenId,
+      amount: U128,
+    ) -> Promise {
+      let price = self.internal_purchase_price(
+        &template_id, &buyer_id, merkle_proof.as_ref(), None, promo_code.as_ref(), 1, Some(&ft_token_id), amount
+      );
+
+      // pay for nft contract storage out of the buyer's storage deposit. 
+      let storage_for_mint = near_to_yoctonear(0.1);
+      let balance = self.storage_deposits.get(&buyer_id).unwrap_or(0);
+      let storage_used = self.internal_storage_slots_used(&buyer_id) as u128 * STORAGE_PER_SALE;
+
+      require!(
+        balance >= storage_used + storage_for_mint,
+        format!(
+          "Requires {} yoctoNEAR of unused storage deposit to pay for minting. Call storage_deposit first.",
+          storage_for_mint
+        )
+      );
+      self.storage_deposits.insert(&buyer_id, &(balance - storage_for_mint));
+      let initial_storage_usage = env::storage_usage();
+
+      let promo_code_hash = promo_code.as_deref().map(hash_promo_code);
+      let mint = self.internal_prepare_mint(
+        &nft_contract_id, &template_id, &buyer_id, price, None, promo_code_hash.as_ref(), Some(&ft_token_id)
+      );
+      let mint_deposit = mint_deposit(1, initial_storage_usage);
+
+      ext_contract::nft_mint(
+        mint.token_id.clone(),
+        mint.metadata,
+        receiver_id.unwrap_or_else(|| buyer_id.clone()),  // gift receiver, or buyer. 
+        perpetual_royalties,
+        mint.size,
+        Some(buyer_id.clone()),  // refund_to_signer
+        self.transfer_rules.get(&template_id),
+        Some(price),
+        memo,
+
+        nft_contract_id.clone(),
+        mint_deposit,
+        GAS_FOR_MINTING
+      ).then(ext_self::resolve_ft_mint(
+        nft_contract_id,
+        template_id,
+        mint.token_id,
+        buyer_id,
+        ft_token_id,
+        amount,
+        price,
+        mint.purchase_recorded,
+        U128(mint.protocol_fee),
+        promo_code_hash,
+        U128(mint_deposit),
+
+        env::current_account_id(),
+        NO_DEPOSIT,
+        GAS_FOR_RESOLVE_FT_MINT
+      ))
+    }
+}
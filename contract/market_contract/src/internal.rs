@@ -1,4 +1,5 @@
 use crate::*;
+use near_sdk::promise_result_as_success;
 
 /// use to generate a unique prefix in our storage collections
 /// (to avoid data collisions)
@@ -9,6 +10,84 @@ pub(crate) fn hash_account_id(account_id: &AccountId) -> CryptoHash {
 }
 
 
+/// Payout object returned from nft_transfer_payout, if the call succeeded
+/// and the payout adds up to the amount passed in. None otherwise. 
+pub(crate) fn payout_from_promise_result(amount: Balance) -> Option<HashMap<AccountId, U128>> {
+  // check payout info returned from nft_transfer_payout method. 
+  promise_result_as_success().and_then(|value| payout_from_value(&value, amount))
+}
+
+
+/// Payout object parsed from the value returned by nft_transfer_payout, if
+/// it adds up to the amount passed in. None otherwise. 
+pub(crate) fn payout_from_value(value: &[u8], amount: Balance) -> Option<HashMap<AccountId, U128>> {
+  // if payout option None, something wrong, refund. 
+  near_sdk::serde_json::from_slice::<Payout>(value)
+      .ok()
+      // returns None if none; otherwise execute logic below. 
+      .and_then(|payout_object| {  
+
+        if payout_object.payout.len() > 10 || payout_object.payout.is_empty() {
+          env::log_str("Either more than 10 royalties or nobody to payout.");
+          None
+        } else {
+
+          let mut remainder = amount;
+
+          // loop through payout and subtract value from remainder.
+          // this check for overflow or any errors and returns None if
+          // there is problems. 
+          for &value in payout_object.payout.values() {
+            remainder = remainder.checked_sub(value.0)?;
+          }
+
+          // check for faulty payout that requires us to pay more or too 
+          // little. Remainder 0 if payout summed to total. Remainder 1 if
+          // rounded off error, like 3333 + 3333 + 3333 = 10000 - 1, the 1. 
+          if remainder == 0 || remainder == 1 {
+            Some(payout_object.payout)  // nothing wrong. 
+          } else {
+            None  // other remainder means something wrong. 
+          }
+        }
+      })
+}
+
+
+/// Storage reserve to attach to nft_mint for quantity tickets: 0.1N each,
+/// less the marketplace storage used since initial_storage_usage to record
+/// them, which the buyer pays out of the reserve. 
+pub(crate) fn mint_deposit(quantity: u64, initial_storage_usage: u64) -> Balance {
+  let reserve = quantity as u128 * near_to_yoctonear(0.1);
+  let storage_cost = env::storage_byte_cost()
+      * Balance::from(env::storage_usage().saturating_sub(initial_storage_usage));
+
+  require!(
+    storage_cost < reserve,
+    "Storage reserve cannot cover recording this purchase."
+  );
+
+  reserve - storage_cost
+}
+
+
+/// Ticket reserved and paid for by `internal_prepare_mint`, ready to mint.
+pub(crate) struct PreparedMint {
+    pub token_id: TokenId,
+    pub metadata: TokenMetadata,
+    pub size: Option<usize>,
+    pub protocol_fee: Balance,
+    pub purchase_recorded: bool,
+}
+
+
+/// current block timestamp in milliseconds, same unit as the
+/// `starts_at`/`expires_at` fields of token metadata. 
+pub(crate) fn block_timestamp_ms() -> u64 {
+    env::block_timestamp() / 1_000_000
+}
+
+
 impl Contract {
     /// internal methods for removing a sale from the market. This returns
     /// the previously removed sale object.
@@ -55,8 +134,387 @@ impl Contract {
     }
 
 
-    /// Refund deposit, usually for storage used. 
-    pub(crate) fn refund_deposit(&mut self, storage_used: u64, to_signer: AccountId) {
+    /// Record template and purchase price of a ticket minted here. An
+    /// existing record is kept, as minting will fail for an existing token. 
+    /// Returns whether the record was inserted. 
+    pub(crate) fn internal_record_purchase(
+      &mut self,
+      nft_contract_id: &AccountId,
+      token_id: &TokenId,
+      template_id: &String,
+      buyer_id: &AccountId,
+      price: U128,
+      protocol_fee: Balance,
+      ft_token_id: Option<&FungibleTokenId>,
+      tier: Option<&String>,
+      promo_code_hash: Option<&Base64VecU8>,
+    ) -> bool {
+      let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+
+      if self.purchases.contains_key(&contract_and_token_id) {
+        return false;
+      }
+
+      self.purchases.insert(&contract_and_token_id, &Purchase {
+        template_id: template_id.clone(),
+        buyer_id: buyer_id.clone(),
+        price,
+        ft_token_id: ft_token_id.cloned(),
+        protocol_fee: U128(protocol_fee),
+        tier: tier.cloned(),
+        promo_code_hash: promo_code_hash.cloned(),
+      });
+      true
+    }
+
+
+    /// Panic if the predecessor doesn't own the template. Returns the owner.
+    pub(crate) fn internal_assert_template_owner(&self, template_id: &String) -> AccountId {
+      let template_owner = expect_lightweight(
+        self.template_owner.get(template_id),
+        "Cannot find template owner. Ensure template_id is correct or created!"
+      );
+
+      require!(
+        env::predecessor_account_id() == template_owner,
+        "Only template owner can do this."
+      );
+
+      template_owner
+    }
+
+
+    /// Check template can still be minted from, and buyer_id can still buy
+    /// from it, and take up one slot. Returns number minted including this one. 
+    pub(crate) fn internal_reserve_mint(&mut self, template_id: &String, buyer_id: &AccountId) -> u64 {
+      require!(
+        !self.cancelled_templates.contains(template_id),
+        "This template has been cancelled. Cannot mint anymore."
+      );
+
+      let max_num_of_mint = expect_lightweight(
+        self.max_mint.get(template_id),
+        "Cannot find template id. Ensure template_id is correct or created!"
+      );
+
+      let minted = self.minted.get(template_id).unwrap_or(0);
+
+      require!(
+        minted < max_num_of_mint,
+        "This template has reached its max minting number. Cannot mint anymore."
+      );
+
+      if let Some(max_per_account) = self.template_max_per_account.get(template_id) {
+        let template_and_account_id = (template_id.clone(), buyer_id.clone());
+        let bought = self.purchases_per_account.get(&template_and_account_id).unwrap_or(0);
+
+        require!(
+          bought < max_per_account,
+          format!(
+            "{} already bought the maximum of {} tickets from this template.",
+            buyer_id,
+            max_per_account
+          )
+        );
+
+        self.purchases_per_account.insert(&template_and_account_id, &(bought + 1));
+      }
+
+      self.minted.insert(template_id, &(minted + 1));
+      minted + 1
+    }
+
+
+    /// Next token ID for a template, like `movie_tickets#00042`, and its
+    /// edition number (42). The sequence never goes back, so IDs of failed 
+    /// or refunded mints aren't reused. 
+    pub(crate) fn internal_next_token_id(&mut self, template_id: &String) -> (TokenId, u64) {
+      let edition = self.template_sequence.get(template_id).unwrap_or(0) + 1;
+      self.template_sequence.insert(template_id, &edition);
+
+      (format!("{}#{:05}", template_id, edition), edition)
+    }
+
+
+    /// Price of one ticket bought from a template, after the promo code if
+    /// given, used up once per ticket of quantity. In NEAR, this is the tier,
+    /// presale or Dutch auction price (see `internal_charge_price`), and
+    /// max_price is the most buyer_id is willing to pay. In a fungible token,
+    /// it's the template's price in that token, and max_price the amount
+    /// transferred; tiers and presale prices are NEAR only. 
+    pub(crate) fn internal_purchase_price(
+      &mut self,
+      template_id: &String,
+      buyer_id: &AccountId,
+      merkle_proof: Option<&Vec<Base64VecU8>>,
+      tier: Option<&String>,
+      promo_code: Option<&String>,
+      quantity: u64,
+      ft_token_id: Option<&FungibleTokenId>,
+      max_price: U128,
+    ) -> U128 {
+      let price = if let Some(ft_token_id) = ft_token_id {
+        // presale prices are in NEAR; in fungible tokens only access is checked. 
+        self.internal_assert_presale_access(template_id, buyer_id, merkle_proof);
+
+        require!(
+          self.template_tiers.get(template_id).is_none(),
+          "Tiered templates can only be bought in NEAR."
+        );
+
+        expect_lightweight(
+          self.template_ft_prices.get(template_id)
+              .and_then(|ft_prices| ft_prices.get(ft_token_id).cloned()),
+          "This template is not for sale in this fungible token."
+        )
+      } else {
+        let price_tier = self.internal_price_tier(template_id, tier);
+        self.internal_charge_price(template_id, buyer_id, merkle_proof, price_tier.as_ref(), max_price)
+      };
+
+      let price = self.internal_apply_promo_code(template_id, promo_code, price, quantity, ft_token_id);
+
+      if ft_token_id.is_some() {
+        require!(
+          max_price.0 >= price.0,
+          format!(
+            "Insufficient price: This ticket costs {}, but only transferred {}",
+            price.0,
+            max_price.0
+          ),
+        );
+      }
+
+      price
+    }
+
+
+    /// Reserve a ticket of a template for buyer_id, in the tier if given, 
+    /// and pay for it: price less protocol fee goes to escrow, and the 
+    /// purchase is recorded for refunds, with the promo code used if any.
+    /// Returns the ticket to mint. 
+    pub(crate) fn internal_prepare_mint(
+      &mut self,
+      nft_contract_id: &AccountId,
+      template_id: &String,
+      buyer_id: &AccountId,
+      price: U128,
+      tier: Option<&String>,
+      promo_code_hash: Option<&Base64VecU8>,
+      ft_token_id: Option<&FungibleTokenId>,
+    ) -> PreparedMint {
+      self.internal_reserve_mint(template_id, buyer_id);
+      self.internal_reserve_tier(template_id, tier);
+      let (token_id, edition) = self.internal_next_token_id(template_id);
+
+      let mut metadata = expect_lightweight(
+        self.template_metadata.get(template_id),
+        "Cannot find template metadata. Ensure template_id is correct or created!"
+      );
+      metadata.copies = Some(edition);
+
+      if let Some(tier) = tier {
+        metadata = metadata_with_tier(metadata, tier);
+      }
+
+      let size = self.internal_price_tier(template_id, tier)
+          .and_then(|price_tier| price_tier.size)
+          .or_else(|| self.nft_size.get(template_id));
+
+      // revenue, less protocol fee, is held in escrow until released to the organizer. 
+      let protocol_fee = self.internal_credit_primary_sale(template_id, ft_token_id, price.0);
+      let purchase_recorded = self.internal_record_purchase(
+        nft_contract_id, &token_id, template_id, buyer_id, price, protocol_fee, ft_token_id, tier, promo_code_hash
+      );
+
+      PreparedMint { token_id, metadata, size, protocol_fee, purchase_recorded }
+    }
+
+
+    /// Reverse what was done when reserving and paying for a mint, after 
+    /// minting failed. 
+    pub(crate) fn internal_revert_mint(
+      &mut self,
+      nft_contract_id: &AccountId,
+      token_id: &TokenId,
+      template_id: &String,
+      buyer_id: &AccountId,
+      ft_token_id: Option<&FungibleTokenId>,
+      price: Balance,
+      protocol_fee: Balance,
+      purchase_recorded: bool,
+    ) {
+      self.internal_debit_revenue(template_id, ft_token_id, price - protocol_fee);
+      self.internal_debit_treasury(ft_token_id, protocol_fee);
+
+      let minted = self.minted.get(template_id).unwrap_or(0);
+      self.minted.insert(template_id, &minted.saturating_sub(1));
+
+      self.internal_release_allowance(template_id, buyer_id);
+
+      if purchase_recorded {
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+        self.purchases.remove(&contract_and_token_id);
+      }
+    }
+
+
+    /// Give back a ticket of buyer_id's allowance on a template, after 
+    /// minting failed or the ticket was refunded. 
+    pub(crate) fn internal_release_allowance(&mut self, template_id: &String, buyer_id: &AccountId) {
+      let template_and_account_id = (template_id.clone(), buyer_id.clone());
+      if let Some(bought) = self.purchases_per_account.get(&template_and_account_id) {
+        self.purchases_per_account.insert(&template_and_account_id, &bought.saturating_sub(1));
+      }
+    }
+
+
+    /// Pay an account in NEAR, or in a fungible token if given. Fungible
+    /// token payments that fail are credited for the receiver to claim. 
+    pub(crate) fn internal_pay(
+      &self,
+      receiver_id: AccountId,
+      ft_token_id: Option<FungibleTokenId>,
+      amount: Balance,
+    ) -> Promise {
+      if let Some(ft_token_id) = ft_token_id {
+        ext_ft::ft_transfer(
+          receiver_id.clone(),
+          U128(amount),
+          None,  // memo
+          ft_token_id.clone(),  // fungible token contract to call
+          1,  // attached yoctoNEAR
+          GAS_FOR_FT_TRANSFER,
+        ).then(ext_self::resolve_ft_payment(
+          receiver_id,
+          ft_token_id,
+          U128(amount),
+          env::current_account_id(),
+          NO_DEPOSIT,
+          GAS_FOR_RESOLVE_FT_PAYMENT,
+        ))
+      } else {
+        Promise::new(receiver_id).transfer(amount)
+      }
+    }
+
+
+    /// Panic if listing price is above the resale cap of the ticket's template.
+    /// Capped tickets can only be listed in the currency they were bought with. 
+    /// Tickets not minted here are still checked by `nft_transfer_payout`. 
+    pub(crate) fn internal_assert_within_resale_cap(
+      &self,
+      contract_and_token_id: &ContractAndTokenId,
+      price: Balance,
+      ft_token_id: Option<&FungibleTokenId>,
+    ) {
+      if let Some(purchase) = self.purchases.get(contract_and_token_id) {
+        let max_resale_bps = self.transfer_rules.get(&purchase.template_id)
+            .and_then(|transfer_rules| transfer_rules.max_resale_bps);
+
+        if let Some(max_resale_bps) = max_resale_bps {
+          require!(
+            purchase.ft_token_id.as_ref() == ft_token_id,
+            "Capped tickets can only be listed in the currency they were bought with."
+          );
+
+          let resale_cap = purchase.price.0 * max_resale_bps as u128 / 10_000u128;
+
+          require!(
+            price <= resale_cap,
+            format!(
+              "Price {} exceeds the maximum resale price {} for this ticket.",
+              price,
+              resale_cap
+            )
+          );
+        }
+      }
+    }
+
+
+    /// transfer token to buyer and get payout from nft contract, then 
+    /// distribute royalties in NEAR, or the fungible token if given. 
+    /// Protocol fee is deducted before royalties are calculated. 
+    pub(crate) fn internal_transfer_payout(
+      &mut self,
+      nft_contract_id: AccountId,
+      token_id: TokenId,
+      approval_id: u64,
+      price: U128,
+      buyer_id: AccountId,
+      ft_token_id: Option<FungibleTokenId>,
+    ) -> Promise {
+      let protocol_fee = self.internal_protocol_fee(price.0, self.secondary_fee_bps);
+
+      // initiate cross-contract call. 
+      // Transfer token to buyer and return payout object for distributing funds.
+      let transfer_payout = ext_contract::nft_transfer_payout(
+        buyer_id.clone(),
+        token_id,
+        approval_id,
+        "payout from market".to_string(),  // memo
+        U128(price.0 - protocol_fee),  // includes royalties
+        10,  // max amount of accounts market can payout
+        nft_contract_id,  // contract to initiate cross contract call to
+        1,  // attached yoctoNEAR
+        GAS_FOR_NFT_TRANSFER,
+      );
+
+      if let Some(ft_token_id) = ft_token_id {
+        transfer_payout.then(ext_self::resolve_ft_purchase(
+          buyer_id,
+          ft_token_id,
+          price,
+          U128(protocol_fee),
+          env::current_account_id(),
+          NO_DEPOSIT,
+          GAS_FOR_RESOLVE_FT_PURCHASE,
+        ))
+      } else {
+        transfer_payout.then(ext_self::resolve_purchase(
+          buyer_id,
+          price,
+          U128(protocol_fee),
+          env::current_account_id(),  // invoking this function on current contract
+          NO_DEPOSIT,
+          GAS_FOR_ROYALTIES,
+        ))
+      }
+    }
+
+
+    /// number of storage slots an account takes up: its sales, auctions, 
+    /// offers and bundles. 
+    pub(crate) fn internal_storage_slots_used(&self, account_id: &AccountId) -> u64 {
+      self.get_supply_by_owner_id(account_id.clone()).0
+        + self.get_auction_supply_by_owner_id(account_id.clone()).0
+        + self.get_offer_supply_by_buyer_id(account_id.clone()).0
+        + self.get_bundle_supply_by_owner_id(account_id.clone()).0
+    }
+
+
+    /// Enforce account has paid enough storage for 1 EXTRA slot. 
+    pub(crate) fn internal_assert_storage_for_new_slot(&self, account_id: &AccountId) {
+      let paid_storage = self.storage_deposits.get(account_id).unwrap_or(0);
+      let storage_required = (
+        self.internal_storage_slots_used(account_id) + 1  // 1 EXTRA
+      ) as u128 * STORAGE_PER_SALE;
+
+      require!(
+        paid_storage >= storage_required,
+        format!(
+          "Insufficient storage paid: {}, for {} sales at {} rate per sale",
+          paid_storage, 
+          storage_required / STORAGE_PER_SALE,
+          STORAGE_PER_SALE,
+        ),
+      );
+    }
+
+
+    /// Refund deposit, usually for storage used. Returns amount refunded. 
+    pub(crate) fn refund_deposit(&mut self, storage_used: u64, to_signer: AccountId) -> Balance {
       let required_cost_to_store_info = env::storage_byte_cost() 
           * Balance::from(storage_used);  // move up if fail. 
     
@@ -74,6 +532,22 @@ impl Contract {
       // refund the predecessor that amount. 
       if refund > 1 {
           Promise::new(to_signer).transfer(refund);
+          refund
+      } else {
+          0
       }
     }
+
+
+    /// Refund whatever was attached above the amount required. Returns 
+    /// amount refunded. 
+    pub(crate) fn internal_refund_surplus(&self, required: Balance, to: AccountId) -> Balance {
+      let surplus = env::attached_deposit() - required;
+
+      if surplus > 0 {
+        Promise::new(to).transfer(surplus);
+      }
+
+      surplus
+    }
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet};
+use near_sdk::collections::{LookupMap, LookupSet, UnorderedMap, UnorderedSet};
 use near_sdk::json_types::{U128, U64, Base64VecU8};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::utils::{is_promise_success};
@@ -14,21 +14,67 @@ use near_helper::{expect_lightweight, near_to_yoctonear, yoctonear_to_near};
 use crate::external::*;
 use crate::internal::*;
 use crate::sale::*;
+use crate::auction::*;
+use crate::batch_mint::*;
+use crate::bundle::*;
+use crate::dutch_auction::*;
+use crate::presale::*;
+use crate::promo::*;
+use crate::tier::*;
 use crate::metadata::*;
 use near_sdk::env::STORAGE_PRICE_PER_BYTE;
 
+mod auction;
+mod batch_mint;
+mod bundle;
+mod dutch_auction;
+mod estimate;
 mod external;
+mod ft_callbacks;
 mod internal;
 mod nft_callbacks;
+mod offer;
+mod presale;
+mod promo;
+mod prune;
 mod sale;
 mod sale_views;
+mod storage;
 mod metadata;
+mod refund;
+mod tier;
+mod revenue;
+mod treasury;
 
 // GAS constants
 const GAS_FOR_ROYALTIES: Gas = Gas(115_000_000_000_000);
 const GAS_FOR_NFT_TRANSFER: Gas = Gas(15_000_000_000_000);
-const GAS_FOR_CALLBACK_AND_MINTING: Gas = Gas(20_000_000_000_000);
 const GAS_FOR_MINTING: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_RESOLVE_MINT: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_RESOLVE_MINT_BATCH: Gas = Gas(25_000_000_000_000);
+const GAS_FOR_NFT_BURN: Gas = Gas(15_000_000_000_000);
+const GAS_FOR_RESOLVE_REFUND: Gas = Gas(25_000_000_000_000);
+const GAS_FOR_RESOLVE_FT_PAYMENT: Gas = Gas(5_000_000_000_000);
+const GAS_FOR_NFT_IS_APPROVED: Gas = Gas(5_000_000_000_000);
+const GAS_FOR_RESOLVE_PRUNE_SALE: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_RESOLVE_BUNDLE_PURCHASE: Gas = Gas(20_000_000_000_000);
+const GAS_FOR_RESOLVE_BUNDLE_DELIVERY: Gas = Gas(20_000_000_000_000);
+const GAS_FOR_FT_TRANSFER: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_RESOLVE_FT_MINT: Gas = Gas(15_000_000_000_000);
+// up to 10 payouts, each an ft_transfer and its resolver. 
+const GAS_FOR_RESOLVE_FT_PURCHASE: Gas = Gas(165_000_000_000_000);
+
+// max tickets an organizer can refund in one call, otherwise not enough GAS. 
+const MAX_REFUNDS_PER_CALL: usize = 6;
+
+// max tickets bought in one pay_and_mint_batch, otherwise not enough GAS. 
+const MAX_BATCH_MINT: u64 = 10;
+
+// max tokens in a bundle, otherwise not enough GAS to transfer them all. 
+const MAX_BUNDLE_SIZE: usize = 5;
+
+// max listings prune_sales checks for validity in one call. 
+const MAX_PRUNE_CHECKS: u64 = 10;
 
 // attach 0 NEAR to call
 const NO_DEPOSIT: Balance = 0;
@@ -85,6 +131,96 @@ pub struct Contract {
 
     /// will be deprecated: size of nft
     pub nft_size: LookupMap<String, usize>,
+
+    /// transfer lockout rules passed on to every ticket minted from template
+    pub transfer_rules: LookupMap<String, TransferRules>,
+
+    /// template and original purchase price of every ticket minted here. 
+    pub purchases: LookupMap<ContractAndTokenId, Purchase>,
+
+    /// funds held for a template: organizer revenue and deposits. Pays out
+    /// refunds, withdrawable by organizer after the revenue release time. 
+    pub template_escrow: LookupMap<String, Balance>,
+
+    /// templates cancelled by their organizer. 
+    pub cancelled_templates: LookupSet<String>,
+
+    /// milliseconds after the event's `expires_at` before revenue is released.
+    pub revenue_release_delay: u64,
+
+    /// protocol fee on primary (template mint) sales, in basis points.
+    pub primary_fee_bps: u16,
+
+    /// protocol fee on secondary (resale) sales, in basis points.
+    pub secondary_fee_bps: u16,
+
+    /// protocol fees collected, withdrawable by owner. 
+    pub treasury_balance: Balance,
+
+    /// whitelisted fungible tokens accepted as payment. 
+    pub ft_token_ids: UnorderedSet<FungibleTokenId>,
+
+    /// template prices in fungible tokens, per fungible token. 
+    pub template_ft_prices: LookupMap<String, HashMap<FungibleTokenId, U128>>,
+
+    /// same as template_escrow but in fungible tokens. 
+    /// Key is (template ID, fungible token ID). 
+    pub ft_template_escrow: LookupMap<(String, FungibleTokenId), Balance>,
+
+    /// protocol fees collected in fungible tokens. 
+    pub ft_treasury_balances: LookupMap<FungibleTokenId, Balance>,
+
+    /// English auctions, keyed like sales. 
+    pub auctions: UnorderedMap<ContractAndTokenId, Auction>,
+
+    /// keep track of all auction IDs for every account
+    pub auctions_by_owner_id: LookupMap<AccountId, UnorderedSet<ContractAndTokenId>>,
+
+    /// descending price of templates sold by Dutch auction
+    pub template_dutch_auction: LookupMap<String, DutchAuction>,
+
+    /// standing offers held in escrow: buyer to amount, per token. 
+    pub offers: LookupMap<ContractAndTokenId, HashMap<AccountId, U128>>,
+
+    /// keep track of all tokens an account made offers on
+    pub offers_by_buyer_id: LookupMap<AccountId, UnorderedSet<ContractAndTokenId>>,
+
+    /// several tokens sold together for one price. 
+    pub bundles: UnorderedMap<String, Bundle>,
+
+    /// keep track of all bundle IDs for every account
+    pub bundles_by_owner_id: LookupMap<AccountId, UnorderedSet<String>>,
+
+    /// last sequence number used for token IDs generated from a template. 
+    pub template_sequence: LookupMap<String, u64>,
+
+    /// most tickets one account can buy from a template, if limited. 
+    pub template_max_per_account: LookupMap<String, u64>,
+
+    /// tickets bought per template and account, keyed by
+    /// (template_id, account_id). 
+    pub purchases_per_account: LookupMap<(String, AccountId), u64>,
+
+    /// presale phase of a template, for allowlisted accounts only. 
+    pub template_presale: LookupMap<String, Presale>,
+
+    /// promo codes of a template, stored by hash. 
+    pub promo_codes: LookupMap<String, Vec<PromoCode>>,
+
+    /// price tiers of a template by name, like adult, child or family. 
+    pub template_tiers: LookupMap<String, HashMap<String, PriceTier>>,
+
+    /// tickets minted per template and tier, keyed by 
+    /// (template_id, tier). 
+    pub tier_minted: LookupMap<(String, String), u64>,
+
+    /// fungible token payments (payouts, refunds and withdrawals) that 
+    /// couldn't be transferred, e.g. receiver not registered on the token,
+    /// claimable with `claim_ft_payment`. 
+    pub unclaimed_ft_payments: LookupMap<(AccountId, FungibleTokenId), Balance>,
+
+    /// bought bundles whose tokens haven't all been delivered yet. 
+    pub bundle_deliveries: LookupMap<String, BundleDelivery>,
 }
 
 
@@ -104,7 +240,33 @@ pub enum StorageKey {
     TokenTemplates,
     TemplateSize,
     Minted,
-    NFTSize
+    NFTSize,
+    TransferRules,
+    Purchases,
+    TemplateEscrow,
+    CancelledTemplates,
+    TemplateFTPrices,
+    FTTemplateEscrow,
+    FTTreasuryBalances,
+    Auctions,
+    AuctionsByOwnerId,
+    AuctionsByOwnerIdInner { account_id_hash: CryptoHash },
+    TemplateDutchAuction,
+    Offers,
+    OffersByBuyerId,
+    OffersByBuyerIdInner { account_id_hash: CryptoHash },
+    Bundles,
+    BundlesByOwnerId,
+    BundlesByOwnerIdInner { account_id_hash: CryptoHash },
+    TemplateSequence,
+    TemplateMaxPerAccount,
+    PurchasesPerAccount,
+    TemplatePresale,
+    PromoCodes,
+    TemplateTiers,
+    TierMinted,
+    UnclaimedFTPayments,
+    BundleDeliveries,
 }
 
 
@@ -123,78 +285,34 @@ impl Contract {
         max_mint: LookupMap::new(StorageKey::TemplateSize),
         minted: LookupMap::new(StorageKey::Minted),
         nft_size: LookupMap::new(StorageKey::NFTSize),
+        transfer_rules: LookupMap::new(StorageKey::TransferRules),
+        purchases: LookupMap::new(StorageKey::Purchases),
+        template_escrow: LookupMap::new(StorageKey::TemplateEscrow),
+        cancelled_templates: LookupSet::new(StorageKey::CancelledTemplates),
+        revenue_release_delay: 0,
+        primary_fee_bps: 0,
+        secondary_fee_bps: 0,
+        treasury_balance: 0,
+        ft_token_ids: UnorderedSet::new(StorageKey::FTTokenIds),
+        template_ft_prices: LookupMap::new(StorageKey::TemplateFTPrices),
+        ft_template_escrow: LookupMap::new(StorageKey::FTTemplateEscrow),
+        ft_treasury_balances: LookupMap::new(StorageKey::FTTreasuryBalances),
+        auctions: UnorderedMap::new(StorageKey::Auctions),
+        auctions_by_owner_id: LookupMap::new(StorageKey::AuctionsByOwnerId),
+        template_dutch_auction: LookupMap::new(StorageKey::TemplateDutchAuction),
+        offers: LookupMap::new(StorageKey::Offers),
+        offers_by_buyer_id: LookupMap::new(StorageKey::OffersByBuyerId),
+        bundles: UnorderedMap::new(StorageKey::Bundles),
+        bundles_by_owner_id: LookupMap::new(StorageKey::BundlesByOwnerId),
+        template_sequence: LookupMap::new(StorageKey::TemplateSequence),
+        template_max_per_account: LookupMap::new(StorageKey::TemplateMaxPerAccount),
+        purchases_per_account: LookupMap::new(StorageKey::PurchasesPerAccount),
+        template_presale: LookupMap::new(StorageKey::TemplatePresale),
+        promo_codes: LookupMap::new(StorageKey::PromoCodes),
+        template_tiers: LookupMap::new(StorageKey::TemplateTiers),
+        tier_minted: LookupMap::new(StorageKey::TierMinted),
+        unclaimed_ft_payments: LookupMap::new(StorageKey::UnclaimedFTPayments),
+        bundle_deliveries: LookupMap::new(StorageKey::BundleDeliveries),
       }
     }
-
-    /// Allow users to deposit storage. This cover cost of storing sale objects
-    /// on the contract. Optional account ID for users to pay for storage for
-    /// other people. 
-    #[payable]
-    pub fn storage_deposit(&mut self, account_id: Option<AccountId>) {
-      let storage_account_id = account_id
-              .map(|a| a.into())
-              // if no specify account_id, use caller of function
-              .unwrap_or_else(env::predecessor_account_id);
-
-      let deposit = env::attached_deposit();
-
-      require!(
-        deposit >= STORAGE_PER_SALE,
-        format!(
-          "Requires minimum deposit of {}",
-          STORAGE_PER_SALE
-        ),
-      );
-
-      let mut balance: u128 = self.storage_deposits.get(&storage_account_id).unwrap_or(0);
-      balance += deposit;
-      self.storage_deposits.insert(&storage_account_id, &balance);
-    }
-
-
-    //// Allow users to withdraw any excess storage that they're not using. 
-    #[payable]
-    pub fn storage_withdraw(&mut self) {
-      assert_one_yocto();
-
-      let owner_id = env::predecessor_account_id();
-
-      // storage deposit stores the excess. If they're not in the map, no
-      // excess. 
-      let mut amount = self.storage_deposits.remove(&owner_id).unwrap_or(0);
-
-      // how many sales the user taking up currently. Returns a set. 
-      let sales = self.by_owner_id.get(&owner_id);
-
-      // get length of that set
-      let len = sales.map(|s| s.len()).unwrap_or_default();
-
-      // how much NEAR is used up for all current sales on the account
-      let diff = u128::from(len) * STORAGE_PER_SALE;
-
-      // excess
-      amount -= diff;
-
-      // if excess > 0, we transfer amount to user. 
-      if amount > 0 {
-        Promise::new(owner_id.clone()).transfer(amount);
-      }
-
-      // Add back storage used up into the map if it's greater than 0. 
-      // this will be freed (withdrawable) when storage no longer need. 
-      if diff > 0 {
-        self.storage_deposits.insert(&owner_id, &diff);
-      }
-    }
-
-    // views
-    /// return the minimum storage for 1 sale
-    pub fn storage_minimum_balance(&self) -> U128 {
-      U128(STORAGE_PER_SALE)
-    }
-
-    /// returns how much storage an account has paid for. 
-    pub fn storage_balance_of(&self, account_id: AccountId) -> U128 {
-      U128(self.storage_deposits.get(&account_id).unwrap_or(0))
-    }
 }
@@ -1,6 +1,6 @@
 use crate::*;
 
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct TokenMetadata {
     pub title: Option<String>,
@@ -16,4 +16,14 @@ pub struct TokenMetadata {
     pub extra: Option<String>,  // anything extra store on chain. Can be stringified JSON. 
     pub reference: Option<String>,  // URL to off-chain JSON file with more info. 
     pub reference_hash: Option<Base64VecU8>,  
+}
+
+/// Rules restricting when a ticket minted from a template can change hands. 
+/// Passed to the nft contract on mint, which enforces them. 
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TransferRules {
+    pub lock_after_use: bool,  // no transfer once any seat of the ticket is used. 
+    pub lock_before_start: Option<u64>,  // milliseconds before `starts_at` where transfer is locked. 
+    pub max_resale_bps: Option<u16>,  // max resale price in basis points of purchase price, e.g. 11000 = 110%. 
 }
\ No newline at end of file
@@ -6,7 +6,12 @@ use crate::*;
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct SaleArgs {
-    pub sale_conditions: SalePriceInYoctoNear,
+    pub sale_conditions: Option<SalePriceInYoctoNear>,
+    pub ft_sale_conditions: Option<HashMap<FungibleTokenId, U128>>,
+    pub auction: Option<AuctionArgs>,  // list as English auction instead of fixed price. 
+    pub accept_offer: Option<AccountId>,  // accept this buyer's offer instead of listing. 
+    pub expires_at: Option<u64>,  // milliseconds, fixed price sale can't be bought after. 
+    pub bundle_id: Option<String>,  // approve token for this bundle instead of listing. 
 }
 
 
@@ -36,53 +41,94 @@ impl NonFungibleTokenApprovalsReceiver for Contract {
       msg: String,
     ) {
       let nft_contract_id = env::predecessor_account_id();
-      let signer_id = env::signer_account_id();
 
+      // anyone can call this, but listings are keyed under the caller as the
+      // nft contract, so a direct caller only lists tokens of its own account,
+      // and buying one asks that account to transfer it. The owner can be any
+      // account, including contracts like DAOs. 
       require!(
-        nft_contract_id != signer_id,
+        nft_contract_id != owner_id,
         format!(
           concat!(
-            "nft_contract_id: {}, signer_id: {}, must be called via cross contract. ",
-            "Error if signer is also contract host."
+            "nft_contract_id: {}, owner_id: {}, must be called via cross contract ",
+            "from the nft contract."
           ),
           nft_contract_id,
-          signer_id
+          owner_id
         ),
-        // "nft_on_approve should only be called via cross-contract call"
       );
 
       require!(
-        owner_id == signer_id,
-        "Only owner can call nft_on_approve. "
+        nft_contract_id != env::current_account_id(),
+        "nft_on_approve cannot be called by the marketplace itself."
       );
 
+      // if all checks pass we can create sale conditions object
+
+      let SaleArgs { sale_conditions, ft_sale_conditions, auction, accept_offer, expires_at, bundle_id } = 
+          near_sdk::serde_json::from_str(&msg).unwrap_or_else(|err|
+            env::panic_str("Message passed in is not valid SaleArgs")
+      );
+
+      // accepting an offer on an unlisted token doesn't take up a slot. 
+      if let Some(buyer_id) = accept_offer {
+        self.internal_accept_offer(nft_contract_id, token_id, approval_id, buyer_id);
+        return;
+      }
+
+      // bundle slot is paid for when the bundle is created. 
+      if let Some(bundle_id) = bundle_id {
+        self.internal_approve_bundle_token(bundle_id, nft_contract_id, token_id, owner_id, approval_id);
+        return;
+      }
+
       // Enforce user has enough storage for 1 EXTRA sale. 
+      self.internal_assert_storage_for_new_slot(&owner_id);
 
-      let storage_amount = self.storage_minimum_balance().0;
-      let owner_paid_storage = self.storage_deposits.get(&signer_id).unwrap_or(0);
-      let signer_storage_required = (
-        self.get_supply_by_owner_id(signer_id).0 + 1  // 1 EXTRA
-      ) as u128 * storage_amount;
+      if let Some(auction) = auction {
+        self.internal_create_auction(nft_contract_id, token_id, owner_id, approval_id, auction);
+        return;
+      }
 
-      require!(
-        owner_paid_storage >= signer_storage_required,
-        format!(
-          "Insufficient storage paid: {}, for {} sales at {} rate per sale",
-          owner_paid_storage, 
-          signer_storage_required / STORAGE_PER_SALE,
-          STORAGE_PER_SALE,
-        ),
-      );
+      let ft_sale_conditions = ft_sale_conditions.unwrap_or_default();
 
-      // if all checks pass we can create sale conditions object
+      if let Some(expires_at) = expires_at {
+        require!(
+          expires_at > block_timestamp_ms(),
+          "Sale expiry must be in the future."
+        );
+      }
 
-      let SaleArgs { sale_conditions } = 
-          near_sdk::serde_json::from_str(&msg).unwrap_or_else(|err|
-            env::panic_str("Message passed in is not valid SaleArgs")
+      require!(
+        sale_conditions.is_some() || !ft_sale_conditions.is_empty(),
+        "Sale needs a price in NEAR or in at least one fungible token."
       );
 
       let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
 
+      // refuse listings above the anti-scalping cap up front. 
+      if let Some(sale_conditions) = sale_conditions {
+        self.internal_assert_within_resale_cap(&contract_and_token_id, sale_conditions.0, None);
+      }
+
+      for (ft_token_id, price) in ft_sale_conditions.iter() {
+        require!(
+          self.ft_token_ids.contains(ft_token_id),
+          format!("Fungible token {} is not accepted on this marketplace.", ft_token_id)
+        );
+
+        self.internal_assert_within_resale_cap(&contract_and_token_id, price.0, Some(ft_token_id));
+      }
+
+      // a fixed price sale replaces an auction nobody has bid on. 
+      if let Some(auction) = self.auctions.get(&contract_and_token_id) {
+        require!(
+          auction.bids.is_empty(),
+          "Token is already on auction with bids."
+        );
+        self.internal_remove_auction(nft_contract_id.clone(), token_id.clone());
+      }
+
       self.sales.insert(
         &contract_and_token_id,
         &Sale {
@@ -91,6 +137,8 @@ impl NonFungibleTokenApprovalsReceiver for Contract {
           nft_contract_id: nft_contract_id.to_string(),
           token_id: token_id.clone(),
           sale_conditions,
+          ft_sale_conditions,
+          expires_at,
         },
       );
 
@@ -0,0 +1,244 @@
+use crate::*;
+
+/// Returned from `get_offers` view call. 
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Offer {
+    pub buyer_id: AccountId,
+    pub amount: U128,
+}
+
+
+#[near_bindgen]
+impl Contract {
+    /// Place an offer on any token, listed or not, with the attached deposit
+    /// held in escrow. Placing again raises your offer by the deposit. A new 
+    /// offer takes up one storage slot, like a sale. 
+    #[payable]
+    pub fn place_offer(
+      &mut self,
+      nft_contract_id: AccountId,
+      token_id: String,
+    ) {
+      let deposit = env::attached_deposit();
+      require!(
+        deposit > 0,
+        "Offer must be larger than 0 yoctoNEAR."
+      );
+
+      let buyer_id = env::predecessor_account_id();
+      let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+
+      if let Some(sale) = self.sales.get(&contract_and_token_id) {
+        require!(
+          sale.owner_id != buyer_id,
+          "Cannot place an offer on your own sale."
+        );
+      }
+
+      let mut offers = self.offers.get(&contract_and_token_id).unwrap_or_default();
+      let amount = offers.get(&buyer_id).map(|a| a.0).unwrap_or(0) + deposit;
+
+      // tickets can't be offered above their resale cap. 
+      self.internal_assert_within_resale_cap(&contract_and_token_id, amount, None);
+
+      if !offers.contains_key(&buyer_id) {
+        self.internal_assert_storage_for_new_slot(&buyer_id);
+
+        let mut by_buyer_id = self.offers_by_buyer_id.get(&buyer_id).unwrap_or_else(|| {
+          UnorderedSet::new(
+            StorageKey::OffersByBuyerIdInner {
+              account_id_hash: hash_account_id(&buyer_id),
+            }
+            .try_to_vec()
+            .unwrap(),
+          )
+        });
+
+        by_buyer_id.insert(&contract_and_token_id);
+        self.offers_by_buyer_id.insert(&buyer_id, &by_buyer_id);
+      }
+
+      offers.insert(buyer_id, U128(amount));
+      self.offers.insert(&contract_and_token_id, &offers);
+    }
+
+    /// Withdraw your offer, refunding it. 
+    #[payable]
+    pub fn withdraw_offer(
+      &mut self,
+      nft_contract_id: AccountId,
+      token_id: String,
+    ) -> U128 {
+      assert_one_yocto();
+
+      let buyer_id = env::predecessor_account_id();
+      let amount = expect_lightweight(
+        self.internal_remove_offer(&nft_contract_id, &token_id, &buyer_id),
+        "No offer found."
+      );
+
+      Promise::new(buyer_id).transfer(amount.0);
+
+      amount
+    }
+
+    /// Sale owner accepts an offer on their listed token. For tokens not 
+    /// listed here, accept an offer by calling nft_approve on the nft contract
+    /// with msg `{"accept_offer": "<buyer_id>"}` instead. 
+    #[payable]
+    pub fn accept_offer(
+      &mut self,
+      nft_contract_id: AccountId,
+      token_id: String,
+      buyer_id: AccountId,
+    ) {
+      assert_one_yocto();
+
+      let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+
+      let sale = expect_lightweight(
+        self.sales.get(&contract_and_token_id),
+        "No Sale. Accept offers on unlisted tokens through nft_approve."
+      );
+
+      require!(
+        env::predecessor_account_id() == sale.owner_id,
+        "Only sale owner can accept offers."
+      );
+
+      require!(!sale.is_expired(), "Sale has expired. List the token again to accept offers.");
+
+      self.internal_accept_offer(nft_contract_id, token_id, sale.approval_id, buyer_id);
+    }
+
+    // views
+    /// returns all offers on a token, for a given unique sale ID 
+    /// (contract + DELIMITER + token ID)
+    pub fn get_offers(&self, nft_contract_id: ContractAndTokenId) -> Vec<Offer> {
+      self.offers.get(&nft_contract_id)
+          .unwrap_or_default()
+          .into_iter()
+          .map(|(buyer_id, amount)| Offer { buyer_id, amount })
+          .collect()
+    }
+
+    /// returns the number of offers for a given account. 
+    pub fn get_offer_supply_by_buyer_id(&self, account_id: AccountId) -> U64 {
+      let by_buyer_id = self.offers_by_buyer_id.get(&account_id);
+
+      if let Some(by_buyer_id) = by_buyer_id {
+        U64(by_buyer_id.len())
+      } else {
+        U64(0)
+      }
+    }
+
+    /// returns paginated unique sale IDs a given account made offers on. 
+    pub fn get_offers_by_buyer_id(
+      &self,
+      account_id: AccountId,
+      from_index: Option<U128>,
+      limit: Option<u64>,
+    ) -> Vec<ContractAndTokenId> {
+      let by_buyer_id = if let Some(by_buyer_id) = self.offers_by_buyer_id.get(&account_id) {
+        by_buyer_id
+      } else {
+        return vec![];
+      };
+
+      let start = u128::from(from_index.unwrap_or(U128(0)));
+
+      by_buyer_id.as_vector().iter()
+          .skip(start as usize)
+          .take(limit.unwrap_or(10) as usize)
+          .collect()
+    }
+}
+
+
+impl Contract {
+    /// Accept a buyer's offer: remove the token's sale, refund every other
+    /// offer on the token, then transfer the token to the buyer and pay out
+    /// the offer. 
+    pub(crate) fn internal_accept_offer(
+      &mut self,
+      nft_contract_id: AccountId,
+      token_id: TokenId,
+      approval_id: u64,
+      buyer_id: AccountId,
+    ) -> Promise {
+      let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+
+      let offers = self.offers.get(&contract_and_token_id).unwrap_or_default();
+      require!(offers.contains_key(&buyer_id), "No offer found from buyer.");
+
+      // token is no longer for sale once an offer is accepted. 
+      if self.sales.get(&contract_and_token_id).is_some() {
+        self.internal_remove_sale(nft_contract_id.clone(), token_id.clone());
+      }
+
+      // an auction with bids has priority over offers. 
+      if let Some(auction) = self.auctions.get(&contract_and_token_id) {
+        require!(
+          auction.bids.is_empty(),
+          "Token is on auction with bids. Cannot accept offers."
+        );
+        self.internal_remove_auction(nft_contract_id.clone(), token_id.clone());
+      }
+
+      let mut amount = U128(0);
+      for offer_buyer_id in offers.keys() {
+        let offer_amount = self.internal_remove_offer(&nft_contract_id, &token_id, offer_buyer_id)
+            .unwrap();
+
+        if offer_buyer_id == &buyer_id {
+          amount = offer_amount;
+        } else {
+          Promise::new(offer_buyer_id.clone()).transfer(offer_amount.0);
+        }
+      }
+
+      self.internal_transfer_payout(
+        nft_contract_id,
+        token_id,
+        approval_id,
+        amount,
+        buyer_id,
+        None,
+      )
+    }
+
+    /// remove a buyer's offer on a token, returning its amount if any. 
+    pub(crate) fn internal_remove_offer(
+      &mut self,
+      nft_contract_id: &AccountId,
+      token_id: &TokenId,
+      buyer_id: &AccountId,
+    ) -> Option<U128> {
+      let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+
+      let mut offers = self.offers.get(&contract_and_token_id)?;
+      let amount = offers.remove(buyer_id)?;
+
+      if offers.is_empty() {
+        self.offers.remove(&contract_and_token_id);
+      } else {
+        self.offers.insert(&contract_and_token_id, &offers);
+      }
+
+      let mut by_buyer_id = expect_lightweight(
+        self.offers_by_buyer_id.get(buyer_id),
+        "No offer found by buyer id."
+      );
+      by_buyer_id.remove(&contract_and_token_id);
+
+      if by_buyer_id.is_empty() {
+        self.offers_by_buyer_id.remove(buyer_id);
+      } else {
+        self.offers_by_buyer_id.insert(buyer_id, &by_buyer_id);
+      }
+
+      Some(amount)
+    }
+}
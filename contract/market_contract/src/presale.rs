@@ -0,0 +1,144 @@
+use crate::*;
+
+/// Early access to a template for allowlisted accounts. Between starts_at
+/// and ends_at only accounts in the Merkle tree with merkle_root can buy,
+/// at price if given; nobody can buy before starts_at. The public sale
+/// starts at ends_at. Times are in milliseconds.
+///
+/// Leaves are sha256 of account IDs; each parent is sha256 of its two
+/// children concatenated smallest first, so proofs don't need directions.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Presale {
+    pub merkle_root: Base64VecU8,
+    pub starts_at: u64,
+    pub ends_at: u64,
+    pub price: Option<U128>,  // NEAR price during presale, template price if None.
+}
+
+// max number of hashes in a proof, enough for any allowlist.
+const MAX_PROOF_LENGTH: usize = 32;
+
+impl Presale {
+    pub(crate) fn assert_valid(&self) {
+      require!(
+        self.merkle_root.0.len() == 32,
+        "Presale Merkle root must be a 32 byte sha256 hash."
+      );
+
+      require!(
+        self.starts_at < self.ends_at,
+        "Presale must start before it ends."
+      );
+    }
+
+    /// whether account_id is in the allowlist, given the sibling hashes on
+    /// the path from its leaf to the root.
+    pub(crate) fn verify(&self, account_id: &AccountId, merkle_proof: &[Base64VecU8]) -> bool {
+      if merkle_proof.len() > MAX_PROOF_LENGTH {
+        return false;
+      }
+
+      let mut hash = env::sha256(account_id.as_bytes());
+
+      for sibling in merkle_proof {
+        hash = if hash <= sibling.0 {
+          env::sha256(&[&hash[..], &sibling.0[..]].concat())
+        } else {
+          env::sha256(&[&sibling.0[..], &hash[..]].concat())
+        };
+      }
+
+      hash == self.merkle_root.0
+    }
+}
+
+
+#[near_bindgen]
+impl Contract {
+    /// Set or remove the presale of a template. Template owner only.
+    /// Replaces the presale previously set.
+    #[payable]
+    pub fn set_template_presale(&mut self, template_id: String, presale: Option<Presale>) {
+      let initial_storage_usage = env::storage_usage();
+
+      self.internal_assert_template_owner(&template_id);
+
+      if let Some(presale) = presale {
+        presale.assert_valid();
+        self.template_presale.insert(&template_id, &presale);
+      } else {
+        self.template_presale.remove(&template_id);
+      }
+
+      let required_storage_in_bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+      self.refund_deposit(required_storage_in_bytes, env::predecessor_account_id());
+    }
+
+    // views
+    /// returns the presale of a template, if any.
+    pub fn get_template_presale(&self, template_id: String) -> Option<Presale> {
+      self.template_presale.get(&template_id)
+    }
+
+    /// whether account_id can buy from a template during its presale with
+    /// the given proof. False if the template has no presale.
+    pub fn is_allowlisted(
+      &self,
+      template_id: String,
+      account_id: AccountId,
+      merkle_proof: Vec<Base64VecU8>,
+    ) -> bool {
+      self.template_presale.get(&template_id)
+          .map(|presale| presale.verify(&account_id, &merkle_proof))
+          .unwrap_or(false)
+    }
+}
+
+
+impl Contract {
+    /// Panic if buyer_id can't buy from the template right now because of
+    /// its presale. Returns the presale price while the presale is on.
+    pub(crate) fn internal_assert_presale_access(
+      &self,
+      template_id: &String,
+      buyer_id: &AccountId,
+      merkle_proof: Option<&Vec<Base64VecU8>>,
+    ) -> Option<U128> {
+      let presale = self.template_presale.get(template_id)?;
+      let now = block_timestamp_ms();
+
+      if now >= presale.ends_at {
+        return None;  // public sale.
+      }
+
+      require!(
+        now >= presale.starts_at,
+        format!("Presale for this template starts at {}.", presale.starts_at)
+      );
+
+      let merkle_proof = expect_lightweight(
+        merkle_proof,
+        "This template is in presale. Pass in the Merkle proof of your account."
+      );
+
+      require!(
+        presale.verify(buyer_id, merkle_proof),
+        format!("{} is not on the presale allowlist.", buyer_id)
+      );
+
+      presale.price
+    }
+
+    /// presale price of a template, if its presale is on.
+    pub(crate) fn internal_presale_price(&self, template_id: &String) -> Option<U128> {
+      let presale = self.template_presale.get(template_id)?;
+      let now = block_timestamp_ms();
+
+      if now >= presale.starts_at && now < presale.ends_at {
+        presale.price
+      } else {
+        None
+      }
+    }
+}
@@ -0,0 +1,220 @@
+use crate::*;
+
+/// Discount given by a promo code: in basis points of the price, or a fixed
+/// amount of yoctoNEAR off.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum Discount {
+    Bps(u16),
+    Amount(U128),
+}
+
+/// Promo code of a template. Only the sha256 hash of the code is stored,
+/// so codes can't be read from the contract. Each ticket bought with it
+/// uses it once. Times are in milliseconds.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PromoCode {
+    pub code_hash: Base64VecU8,
+    pub discount: Discount,
+    pub max_uses: u64,
+    pub used: u64,
+    pub expires_at: Option<u64>,
+}
+
+// max number of promo codes on a template.
+const MAX_PROMO_CODES: usize = 20;
+
+/// sha256 hash of a promo code, as stored on its template. 
+pub(crate) fn hash_promo_code(code: &str) -> Base64VecU8 {
+  Base64VecU8(env::sha256(code.as_bytes()))
+}
+
+impl PromoCode {
+    pub(crate) fn assert_valid(&self) {
+      require!(
+        self.code_hash.0.len() == 32,
+        "Promo code hash must be a 32 byte sha256 hash."
+      );
+
+      if let Discount::Bps(bps) = self.discount {
+        require!(bps <= 10_000, "Promo code discount cannot be more than 10000 bps.");
+      }
+
+      require!(self.max_uses > 0, "Promo code must have at least 1 use.");
+    }
+
+    pub(crate) fn is_expired(&self) -> bool {
+      self.expires_at.map(|expires_at| block_timestamp_ms() >= expires_at).unwrap_or(false)
+    }
+
+    /// price after discount, never below zero.
+    pub(crate) fn discounted(&self, price: Balance) -> Balance {
+      match &self.discount {
+        Discount::Bps(bps) => price - price * *bps as u128 / 10_000u128,
+        Discount::Amount(amount) => price.saturating_sub(amount.0),
+      }
+    }
+}
+
+
+#[near_bindgen]
+impl Contract {
+    /// Add a promo code to a template, given the sha256 hash of the code.
+    /// Template owner only. Replaces a code with the same hash, resetting
+    /// its uses.
+    #[payable]
+    pub fn add_promo_code(
+      &mut self,
+      template_id: String,
+      code_hash: Base64VecU8,
+      discount: Discount,
+      max_uses: u64,
+      expires_at: Option<u64>,
+    ) {
+      let initial_storage_usage = env::storage_usage();
+
+      self.internal_assert_template_owner(&template_id);
+
+      let promo_code = PromoCode { code_hash, discount, max_uses, used: 0, expires_at };
+      promo_code.assert_valid();
+
+      let mut promo_codes = self.promo_codes.get(&template_id).unwrap_or_default();
+      promo_codes.retain(|existing| existing.code_hash.0 != promo_code.code_hash.0);
+
+      require!(
+        promo_codes.len() < MAX_PROMO_CODES,
+        format!("A template cannot have more than {} promo codes.", MAX_PROMO_CODES)
+      );
+
+      promo_codes.push(promo_code);
+      self.promo_codes.insert(&template_id, &promo_codes);
+
+      let required_storage_in_bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+      self.refund_deposit(required_storage_in_bytes, env::predecessor_account_id());
+    }
+
+    /// Remove a promo code from a template. Template owner only.
+    #[payable]
+    pub fn remove_promo_code(&mut self, template_id: String, code_hash: Base64VecU8) {
+      assert_one_yocto();
+
+      self.internal_assert_template_owner(&template_id);
+
+      let mut promo_codes = self.promo_codes.get(&template_id).unwrap_or_default();
+      promo_codes.retain(|existing| existing.code_hash.0 != code_hash.0);
+
+      if promo_codes.is_empty() {
+        self.promo_codes.remove(&template_id);
+      } else {
+        self.promo_codes.insert(&template_id, &promo_codes);
+      }
+    }
+
+    // views
+    /// returns promo codes of a template, by hash, with how often they've
+    /// been used.
+    pub fn get_promo_codes(&self, template_id: String) -> Vec<PromoCode> {
+      self.promo_codes.get(&template_id).unwrap_or_default()
+    }
+
+    /// returns how many more times a promo code can be used, zero if it
+    /// expired. None if the template has no code with this hash.
+    pub fn get_promo_code_uses_left(&self, template_id: String, code_hash: Base64VecU8) -> Option<U64> {
+      self.promo_codes.get(&template_id)?
+          .into_iter()
+          .find(|promo_code| promo_code.code_hash.0 == code_hash.0)
+          .map(|promo_code| if promo_code.is_expired() {
+            U64(0)
+          } else {
+            U64(promo_code.max_uses.saturating_sub(promo_code.used))
+          })
+    }
+}
+
+
+impl Contract {
+    /// Promo code of a template matching the code, panics if there's none
+    /// or it expired.
+    fn internal_find_promo_code(&self, template_id: &String, code: &str) -> (Vec<PromoCode>, usize) {
+      let code_hash = hash_promo_code(code);
+      let promo_codes = self.promo_codes.get(template_id).unwrap_or_default();
+
+      let index = expect_lightweight(
+        promo_codes.iter().position(|promo_code| promo_code.code_hash.0 == code_hash.0),
+        "Invalid promo code."
+      );
+
+      require!(!promo_codes[index].is_expired(), "This promo code has expired.");
+
+      (promo_codes, index)
+    }
+
+    /// Price of one ticket after applying a promo code, if given, for
+    /// quantity tickets. Uses up the code once per ticket, see 
+    /// `internal_restore_promo_code`. Fixed amount codes only apply to
+    /// purchases in NEAR.
+    pub(crate) fn internal_apply_promo_code(
+      &mut self,
+      template_id: &String,
+      promo_code: Option<&String>,
+      price: U128,
+      quantity: u64,
+      ft_token_id: Option<&FungibleTokenId>,
+    ) -> U128 {
+      let code = if let Some(code) = promo_code {
+        code
+      } else {
+        return price;
+      };
+
+      let (mut promo_codes, index) = self.internal_find_promo_code(template_id, code);
+      let promo_code = &mut promo_codes[index];
+
+      require!(
+        promo_code.used + quantity <= promo_code.max_uses,
+        format!(
+          "This promo code can only be used {} more times.",
+          promo_code.max_uses.saturating_sub(promo_code.used)
+        )
+      );
+
+      if ft_token_id.is_some() {
+        require!(
+          matches!(promo_code.discount, Discount::Bps(_)),
+          "Fixed amount promo codes only apply to purchases in NEAR."
+        );
+      }
+
+      promo_code.used += quantity;
+      let discounted = promo_code.discounted(price.0);
+
+      self.promo_codes.insert(template_id, &promo_codes);
+      U128(discounted)
+    }
+
+    /// Give back quantity uses of a promo code, by hash, after minting failed
+    /// or a ticket was refunded. Nothing to do without a code, or if the code
+    /// has since been removed. 
+    pub(crate) fn internal_restore_promo_code(
+      &mut self,
+      template_id: &String,
+      code_hash: Option<&Base64VecU8>,
+      quantity: u64,
+    ) {
+      let code_hash = if let Some(code_hash) = code_hash { code_hash } else { return };
+      let mut promo_codes = self.promo_codes.get(template_id).unwrap_or_default();
+
+      if let Some(promo_code) = promo_codes.iter_mut().find(|promo_code| promo_code.code_hash.0 == code_hash.0) {
+        promo_code.used = promo_code.used.saturating_sub(quantity);
+        self.promo_codes.insert(template_id, &promo_codes);
+      }
+    }
+
+    /// Price of one ticket after a promo code, without using it up.
+    pub(crate) fn internal_promo_price(&self, template_id: &String, code: &str, price: U128) -> U128 {
+      let (promo_codes, index) = self.internal_find_promo_code(template_id, code);
+      U128(promo_codes[index].discounted(price.0))
+    }
+}
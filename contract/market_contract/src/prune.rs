@@ -0,0 +1,90 @@
+use crate::*;
+use near_sdk::promise_result_as_success;
+
+#[near_bindgen]
+impl Contract {
+    /// Remove stale listings, freeing their owners' storage slots. Anyone can
+    /// call this. Goes through a page of sales: expired sales are removed
+    /// right away, and the nft contract is asked whether the rest are still
+    /// approved (owner may have transferred the token or revoked approval). 
+    /// Those that aren't are removed in `resolve_prune_sale`. Returns the 
+    /// number of expired sales removed. 
+    pub fn prune_sales(
+      &mut self,
+      from_index: Option<U128>,
+      limit: Option<u64>,
+    ) -> U64 {
+      let limit = limit.unwrap_or(MAX_PRUNE_CHECKS);
+      require!(
+        limit <= MAX_PRUNE_CHECKS,
+        format!("Cannot check more than {} sales per call.", MAX_PRUNE_CHECKS)
+      );
+
+      let start = u128::from(from_index.unwrap_or(U128(0)));
+
+      // collect first, as removing reorders the sales. 
+      let sales: Vec<Sale> = self.sales.values()
+          .skip(start as usize)
+          .take(limit as usize)
+          .collect();
+
+      let mut pruned = 0;
+      for sale in sales {
+        let nft_contract_id: AccountId = sale.nft_contract_id.parse().unwrap();
+
+        if sale.is_expired() {
+          self.internal_remove_sale(nft_contract_id, sale.token_id);
+          pruned += 1;
+          continue;
+        }
+
+        ext_contract::nft_is_approved(
+          sale.token_id.clone(),
+          env::current_account_id(),
+          Some(sale.approval_id),
+          nft_contract_id.clone(),
+          NO_DEPOSIT,
+          GAS_FOR_NFT_IS_APPROVED,
+        )
+        .then(ext_self::resolve_prune_sale(
+          nft_contract_id,
+          sale.token_id,
+          sale.approval_id,
+          env::current_account_id(),
+          NO_DEPOSIT,
+          GAS_FOR_RESOLVE_PRUNE_SALE,
+        ));
+      }
+
+      U64(pruned)
+    }
+
+    /// Resolve promise when calling nft_is_approved. Remove the sale if we 
+    /// are no longer approved for it, or the token no longer exists. 
+    /// Returns whether the sale was removed. 
+    #[private]
+    pub fn resolve_prune_sale(
+      &mut self,
+      nft_contract_id: AccountId,
+      token_id: TokenId,
+      approval_id: u64,
+    ) -> bool {
+      let is_approved = promise_result_as_success().map(|value| {
+        near_sdk::serde_json::from_slice::<bool>(&value).unwrap_or(true)
+      });
+
+      if is_approved == Some(true) {
+        return false;
+      }
+
+      // sale may have been bought or listed again in the meantime. 
+      let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+      match self.sales.get(&contract_and_token_id) {
+        Some(sale) if sale.approval_id == approval_id => {
+          self.internal_remove_sale(nft_contract_id, token_id);
+          true
+        },
+        _ => false
+      }
+    }
+}
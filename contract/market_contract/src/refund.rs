@@ -0,0 +1,185 @@
+use crate::*;
+use near_sdk::promise_result_as_success;
+
+#[near_bindgen]
+impl Contract {
+    /// Cancel a template. No more tickets can be minted, and the organizer
+    /// can refund tickets already sold with `refund_tickets`. 
+    #[payable]
+    pub fn cancel_template(&mut self, template_id: String) {
+      assert_one_yocto();
+
+      self.internal_assert_template_owner(&template_id);
+
+      self.cancelled_templates.insert(&template_id);
+    }
+
+    /// Buyer surrenders their ticket for a refund of its purchase price. 
+    /// Only possible before the event starts, unless the template is cancelled. 
+    #[payable]
+    pub fn refund_ticket(
+      &mut self,
+      nft_contract_id: AccountId,
+      token_id: TokenId,
+    ) -> Promise {
+      assert_one_yocto();
+
+      let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+      let purchase = expect_lightweight(
+        self.purchases.get(&contract_and_token_id),
+        "Ticket was not minted through this marketplace."
+      );
+
+      if !self.cancelled_templates.contains(&purchase.template_id) {
+        let starts_at = self.template_metadata.get(&purchase.template_id)
+            .and_then(|metadata| metadata.starts_at);
+
+        if let Some(starts_at) = starts_at {
+          require!(
+            block_timestamp_ms() < starts_at,
+            "Event has already started. Cannot refund ticket."
+          );
+        }
+      }
+
+      self.internal_refund(
+        nft_contract_id, 
+        token_id, 
+        Some(env::predecessor_account_id())
+      )
+    }
+
+    /// Organizer refunds tickets in bulk (e.g. event cancelled). Refunds go
+    /// to whoever holds each ticket now. 
+    #[payable]
+    pub fn refund_tickets(
+      &mut self,
+      nft_contract_id: AccountId,
+      token_ids: Vec<TokenId>,
+    ) {
+      assert_one_yocto();
+
+      require!(
+        token_ids.len() <= MAX_REFUNDS_PER_CALL,
+        format!("Cannot refund more than {} tickets per call.", MAX_REFUNDS_PER_CALL)
+      );
+
+      for token_id in token_ids {
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+        let purchase = expect_lightweight(
+          self.purchases.get(&contract_and_token_id),
+          "Ticket was not minted through this marketplace."
+        );
+
+        self.internal_assert_template_owner(&purchase.template_id);
+
+        self.internal_refund(nft_contract_id.clone(), token_id, None);
+      }
+    }
+
+    /// Resolve promise when calling nft_burn. Pay refund (in the currency the
+    /// ticket was bought with) to the burnt ticket's owner if burn succeeded, 
+    /// else put the amount back into escrow and the fee back into treasury. 
+    #[private]
+    pub fn resolve_refund(
+      &mut self,
+      nft_contract_id: AccountId,
+      token_id: TokenId,
+      template_id: String,
+      amount: U128,
+      ft_token_id: Option<FungibleTokenId>,
+      protocol_fee: U128,
+    ) -> U128 {
+      let owner_id = promise_result_as_success().and_then(|value| {
+        near_sdk::serde_json::from_slice::<AccountId>(&value).ok()
+      });
+
+      let owner_id = if let Some(owner_id) = owner_id {
+        owner_id
+      } else {
+        // burn failed, return reserved amount to escrow and treasury. 
+        self.internal_credit_revenue(&template_id, ft_token_id.as_ref(), amount.0 - protocol_fee.0);
+        self.internal_credit_treasury(ft_token_id.as_ref(), protocol_fee.0);
+        return U128(0);
+      };
+
+      // buyer can buy this ticket again, within the template's max_per_account,
+      // in the same tier and with the same promo code. 
+      let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+      if let Some(purchase) = self.purchases.remove(&contract_and_token_id) {
+        self.internal_release_allowance(&template_id, &purchase.buyer_id);
+        self.internal_revert_tier(&template_id, purchase.tier.as_ref());
+        self.internal_restore_promo_code(&template_id, purchase.promo_code_hash.as_ref(), 1);
+      }
+
+      // release the slot so it can be minted again. 
+      let minted = self.minted.get(&template_id).unwrap_or(0);
+      self.minted.insert(&template_id, &minted.saturating_sub(1));
+
+      self.internal_pay(owner_id, ft_token_id, amount.0);
+
+      amount
+    }
+
+    // views
+    /// returns whether the template has been cancelled. 
+    pub fn is_template_cancelled(&self, template_id: String) -> bool {
+      self.cancelled_templates.contains(&template_id)
+    }
+}
+
+impl Contract {
+    /// Reserve the purchase price from escrow, burn the ticket on the nft 
+    /// contract, then resolve the refund. 
+    pub(crate) fn internal_refund(
+      &mut self,
+      nft_contract_id: AccountId,
+      token_id: TokenId,
+      owner_id: Option<AccountId>,
+    ) -> Promise {
+      let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMITER, token_id);
+      let purchase = expect_lightweight(
+        self.purchases.get(&contract_and_token_id),
+        "Ticket was not minted through this marketplace."
+      );
+
+      // escrow only got the price less protocol fee; the fee is reversed
+      // from the treasury. 
+      let ft_token_id = purchase.ft_token_id.as_ref();
+      let protocol_fee = purchase.protocol_fee.0;
+      let from_escrow = purchase.price.0 - protocol_fee;
+
+      let balance = self.internal_escrow_balance(&purchase.template_id, ft_token_id);
+      require!(
+        balance >= from_escrow,
+        "Not enough funds in escrow to refund this ticket. Contact the organizer."
+      );
+
+      require!(
+        self.internal_treasury_balance(ft_token_id) >= protocol_fee,
+        "Not enough funds in treasury to return the protocol fee. Contact the marketplace."
+      );
+
+      self.internal_set_escrow_balance(&purchase.template_id, ft_token_id, balance - from_escrow);
+      self.internal_debit_treasury(ft_token_id, protocol_fee);
+
+      ext_contract::nft_burn(
+        token_id.clone(),
+        owner_id,
+        nft_contract_id.clone(),
+        1,  // attached yoctoNEAR
+        GAS_FOR_NFT_BURN,
+      )
+      .then(ext_self::resolve_refund(
+        nft_contract_id,
+        token_id,
+        purchase.template_id,
+        purchase.price,
+        purchase.ft_token_id,
+        purchase.protocol_fee,
+        env::current_account_id(),
+        NO_DEPOSIT,
+        GAS_FOR_RESOLVE_REFUND,
+      ))
+    }
+}
@@ -0,0 +1,160 @@
+use crate::*;
+
+/// Returned from `get_revenue_balance` view call. 
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RevenueBalance {
+    pub pending: U128,  // held until release time. 
+    pub available: U128,  // withdrawable by organizer. 
+    pub release_at: Option<u64>,  // milliseconds, None if released immediately. 
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Organizer deposits extra funds into the template's escrow, e.g. to
+    /// cover refunds. 
+    #[payable]
+    pub fn deposit_escrow(&mut self, template_id: String) {
+      self.internal_assert_template_owner(&template_id);
+
+      let deposit = env::attached_deposit();
+      require!(deposit > 0, "Requires attached deposit larger than 0 yoctoNEAR.");
+
+      self.internal_credit_revenue(&template_id, None, deposit);
+    }
+
+    /// Organizer withdraws revenue of a template once released. Withdraws
+    /// everything if no amount is passed in. Pass in ft_token_id to withdraw
+    /// revenue paid in that fungible token instead of NEAR. 
+    #[payable]
+    pub fn withdraw_revenue(
+      &mut self, 
+      template_id: String, 
+      amount: Option<U128>,
+      ft_token_id: Option<FungibleTokenId>,
+    ) -> U128 {
+      assert_one_yocto();
+
+      let template_owner = self.internal_assert_template_owner(&template_id);
+
+      if let Some(release_at) = self.internal_revenue_release_at(&template_id) {
+        require!(
+          block_timestamp_ms() >= release_at,
+          format!("Revenue is held until {}.", release_at)
+        );
+      }
+
+      let balance = self.internal_escrow_balance(&template_id, ft_token_id.as_ref());
+      let amount = amount.map(|a| a.0).unwrap_or(balance);
+
+      require!(
+        amount <= balance,
+        format!("Cannot withdraw {}, only {} available.", amount, balance)
+      );
+
+      self.internal_set_escrow_balance(&template_id, ft_token_id.as_ref(), balance - amount);
+
+      if amount > 0 {
+        self.internal_pay(template_owner, ft_token_id, amount);
+      }
+
+      U128(amount)
+    }
+
+    /// Set how long after the event ends revenue is held. Contract owner only. 
+    pub fn set_revenue_release_delay(&mut self, revenue_release_delay: u64) {
+      require!(
+        env::predecessor_account_id() == self.owner_id,
+        "Only contract owner can set revenue release delay."
+      );
+
+      self.revenue_release_delay = revenue_release_delay;
+    }
+
+    // views
+    /// returns revenue of a template still held and available to withdraw,
+    /// in NEAR or in the given fungible token. 
+    pub fn get_revenue_balance(
+      &self, 
+      template_id: String, 
+      ft_token_id: Option<FungibleTokenId>
+    ) -> RevenueBalance {
+      let balance = self.internal_escrow_balance(&template_id, ft_token_id.as_ref());
+      let release_at = self.internal_revenue_release_at(&template_id);
+
+      let released = match release_at {
+        Some(release_at) => block_timestamp_ms() >= release_at,
+        None => true
+      };
+
+      if released {
+        RevenueBalance { pending: U128(0), available: U128(balance), release_at }
+      } else {
+        RevenueBalance { pending: U128(balance), available: U128(0), release_at }
+      }
+    }
+
+    /// returns how long after the event ends revenue is held. 
+    pub fn get_revenue_release_delay(&self) -> u64 {
+      self.revenue_release_delay
+    }
+}
+
+impl Contract {
+    /// balance of a template's escrow in NEAR, or in a fungible token. 
+    pub(crate) fn internal_escrow_balance(
+      &self,
+      template_id: &String,
+      ft_token_id: Option<&FungibleTokenId>,
+    ) -> Balance {
+      if let Some(ft_token_id) = ft_token_id {
+        self.ft_template_escrow.get(&(template_id.clone(), ft_token_id.clone())).unwrap_or(0)
+      } else {
+        self.template_escrow.get(template_id).unwrap_or(0)
+      }
+    }
+
+    pub(crate) fn internal_set_escrow_balance(
+      &mut self,
+      template_id: &String,
+      ft_token_id: Option<&FungibleTokenId>,
+      balance: Balance,
+    ) {
+      if let Some(ft_token_id) = ft_token_id {
+        self.ft_template_escrow.insert(&(template_id.clone(), ft_token_id.clone()), &balance);
+      } else {
+        self.template_escrow.insert(template_id, &balance);
+      }
+    }
+
+    /// credit revenue (or organizer deposit) to a template's escrow. 
+    pub(crate) fn internal_credit_revenue(
+      &mut self,
+      template_id: &String,
+      ft_token_id: Option<&FungibleTokenId>,
+      amount: Balance,
+    ) {
+      let balance = self.internal_escrow_balance(template_id, ft_token_id);
+      self.internal_set_escrow_balance(template_id, ft_token_id, balance + amount);
+    }
+
+    /// take back revenue credited to a template's escrow, e.g. on failed mint.
+    pub(crate) fn internal_debit_revenue(
+      &mut self,
+      template_id: &String,
+      ft_token_id: Option<&FungibleTokenId>,
+      amount: Balance,
+    ) {
+      let balance = self.internal_escrow_balance(template_id, ft_token_id);
+      self.internal_set_escrow_balance(template_id, ft_token_id, balance.saturating_sub(amount));
+    }
+
+    /// When template revenue is released: the event's `expires_at` (or
+    /// `starts_at` if no expiry) plus release delay. None if no event time. 
+    pub(crate) fn internal_revenue_release_at(&self, template_id: &String) -> Option<u64> {
+      let metadata = self.template_metadata.get(template_id)?;
+      let event_end = metadata.expires_at.or(metadata.starts_at)?;
+
+      Some(event_end + self.revenue_release_delay)
+    }
+}
@@ -1,5 +1,4 @@
 use crate::*;
-use near_sdk::promise_result_as_success;
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
@@ -8,7 +7,40 @@ pub struct Sale {
     pub approval_id: u64,
     pub nft_contract_id: String,
     pub token_id: String,
-    pub sale_conditions: SalePriceInYoctoNear,
+    pub sale_conditions: Option<SalePriceInYoctoNear>,  // None if not for sale in NEAR. 
+    pub ft_sale_conditions: HashMap<FungibleTokenId, U128>,  // price per fungible token. 
+    pub expires_at: Option<u64>,  // milliseconds, sale can't be bought after. 
+}
+
+impl Sale {
+    pub(crate) fn is_expired(&self) -> bool {
+      match self.expires_at {
+        Some(expires_at) => block_timestamp_ms() >= expires_at,
+        None => false
+      }
+    }
+}
+
+/// Record of a ticket minted through a template. 
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Purchase {
+    pub template_id: String,
+    pub buyer_id: AccountId,  // counted against the template's max_per_account. 
+    pub price: U128,  // original purchase price (face value). 
+    pub ft_token_id: Option<FungibleTokenId>,  // None if bought with NEAR. 
+    pub protocol_fee: U128,  // part of price that went to the treasury. 
+    pub tier: Option<String>,  // None if the template has no tiers. 
+    pub promo_code_hash: Option<Base64VecU8>,  // promo code used, given back on refund. 
+}
+
+/// Token ID assigned to a ticket bought from a template, and the deposit
+/// refunded above its price and storage reserve. 
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MintedTicket {
+    pub token_id: TokenId,
+    pub refund: U128,
 }
 
 
@@ -19,7 +51,8 @@ impl Contract {
 
     /// Generate Template
     /// Just for you to try out, we did not assert only
-    /// shop owner could call this function. 
+    /// shop owner could call this function. Attach at least the storage
+    /// needed (see estimate_generate_template_deposit); returns refund. 
     #[payable]
     pub fn generate_template(
       &mut self,
@@ -28,20 +61,13 @@ impl Contract {
       max_num_of_mint: u64,
       metadata: TokenMetadata,
       perpetual_royalties: Option<HashMap<AccountId, u16>>,
-      size: Option<usize>
-    ) {
+      size: Option<usize>,
+      transfer_rules: Option<TransferRules>,
+      dutch_auction: Option<DutchAuction>,
+      max_per_account: Option<u64>,  // most tickets one account can buy. 
+    ) -> U128 {
       let initial_storage_usage = env::storage_usage();
 
-      require!(
-        env::attached_deposit() >= near_to_yoctonear(0.1),
-        "Please attach exactly 0.1 NEAR for storage. Extra will be refunded."
-      );
-
-      require!(
-        env::attached_deposit() <= near_to_yoctonear(0.101),
-        "Please attach exactly 0.1 NEAR for storage. Extra will be refunded."
-      );
-
       // Actually may have to check whether template exist same. 
       // But one isn't sure how to do that without using much Gas + CPU,
       // so we'll check only the template_id exists. 
@@ -61,26 +87,48 @@ impl Contract {
           self.nft_size.insert(&template_id, &size);
         }
 
+        if let Some(transfer_rules) = transfer_rules {
+          self.transfer_rules.insert(&template_id, &transfer_rules);
+        }
+
+        if let Some(dutch_auction) = dutch_auction {
+          dutch_auction.assert_valid();
+          self.template_dutch_auction.insert(&template_id, &dutch_auction);
+        }
+
+        if let Some(max_per_account) = max_per_account {
+          require!(max_per_account > 0, "max_per_account must be at least 1.");
+          self.template_max_per_account.insert(&template_id, &max_per_account);
+        }
+
         // And we'll totally ignore perpetual royalties for now. 
       }
 
       let required_storage_in_bytes = env::storage_usage() - initial_storage_usage;
 
-      self.refund_deposit(required_storage_in_bytes, env::predecessor_account_id());
-
+      // panics if not enough attached for storage; refunds the rest. 
+      U128(self.refund_deposit(required_storage_in_bytes, env::predecessor_account_id()))
     }
 
-    /// Buy an mint-on-demand nft
+    /// Buy an mint-on-demand nft. For Dutch auction templates, price is the
+    /// most you're willing to pay and you're charged the current price. 
+    /// Attach at least price + 0.1N. Token ID is generated from the template
+    /// and returned, with what was refunded above price + 0.1N. Storage left
+    /// over from the 0.1N is refunded separately on minting. 
     #[payable]
     pub fn pay_and_mint(
       &mut self,
       nft_contract_id: AccountId,
       template_id: String,
       price: U128,
-      token_id: TokenId,
       // issued_at include in the future. 
       perpetual_royalties: Option<HashMap<AccountId, u16>>,  // temporarily. 
-    ) {
+      receiver_id: Option<AccountId>,  // gift to this account, payer if None. 
+      memo: Option<String>,  // gift message, carried into the mint event. 
+      merkle_proof: Option<Vec<Base64VecU8>>,  // required during presale. 
+      promo_code: Option<String>,
+      tier: Option<String>,  // required for tiered templates. 
+    ) -> MintedTicket {
       require!(
         env::attached_deposit() >= (u128::from(price) + near_to_yoctonear(0.1)),
         concat!(
@@ -89,50 +137,62 @@ impl Contract {
         )
       );
 
-      require!(
-        env::attached_deposit() <= (u128::from(price) + near_to_yoctonear(0.101)),
-        "You attached too much near. This function requires EXACTLY price + 0.1N."
-      );
+      // payer may be a contract (DAO, multisig) buying on behalf of users. 
+      let payer_id = env::predecessor_account_id();
 
-      let max_num_of_mint = expect_lightweight(
-        self.max_mint.get(&template_id),
-        "Cannot find template id. Ensure template_id is correct or created!"
+      // for tiered, presale and Dutch auction templates, price is the most you're willing to pay. 
+      let price = self.internal_purchase_price(
+        &template_id, &payer_id, merkle_proof.as_ref(), tier.as_ref(), promo_code.as_ref(), 1, None, price
       );
 
-      let mut minted = match self.minted.get(&template_id) {
-        Some(value) => value,
-        None => 0
-      };
-
-      require!(
-        minted <= max_num_of_mint,
-        "This template has reached its max minting number. Cannot mint anymore."
+      // anything attached above the price and storage reserve is refunded. 
+      let refund = self.internal_refund_surplus(
+        price.0 + near_to_yoctonear(0.1),
+        payer_id.clone()
       );
 
-      minted += 1;
-      self.minted.insert(&template_id, &minted);
-
-      let nft_seller_id = expect_lightweight(
-        self.template_owner.get(&template_id),
-        "Cannot find template owner. Ensure template_id is correct or created!"
+      let promo_code_hash = promo_code.as_deref().map(hash_promo_code);
+      let initial_storage_usage = env::storage_usage();
+      let mint = self.internal_prepare_mint(
+        &nft_contract_id, &template_id, &payer_id, price, tier.as_ref(), promo_code_hash.as_ref(), None
       );
+      let mint_deposit = mint_deposit(1, initial_storage_usage);
+
+      ext_contract::nft_mint(
+        mint.token_id.clone(),
+        mint.metadata,
+        receiver_id.unwrap_or_else(|| payer_id.clone()),  // receiver of NFT, payer if not a gift. 
+        perpetual_royalties,
+        mint.size,
+        Some(payer_id.clone()),  // refund_to_signer, payer even for gifts. 
+        self.transfer_rules.get(&template_id),
+        Some(price),
+        memo,
+
+        nft_contract_id.clone(),
+        mint_deposit,
+        GAS_FOR_MINTING
+      ).then(ext_self::resolve_mint(
+        nft_contract_id,
+        template_id,
+        mint.token_id.clone(),
+        price,
+        payer_id,
+        mint.purchase_recorded,
+        U128(mint.protocol_fee),
+        tier,
+        promo_code_hash,
+        U128(mint_deposit),
+
+        env::current_account_id(),
+        NO_DEPOSIT,
+        GAS_FOR_RESOLVE_MINT
+      ));
 
-      Promise::new(nft_seller_id).transfer(price.into()).then(
-        ext_self::on_nft_mint(
-          price,
-          nft_contract_id,
-          template_id,
-          token_id,
-          env::signer_account_id(),  // receiver of NFT is signer. 
-          perpetual_royalties,
-          Some(env::signer_account_id()),  // refund_to_signer
-  
-          env::current_account_id(),
-          near_to_yoctonear(0.1),
-          GAS_FOR_CALLBACK_AND_MINTING
-        )
-      );
-      
+      MintedTicket {
+        token_id: mint.token_id,
+        refund: U128(refund),
+      }
     }
 
 
@@ -147,10 +207,14 @@ impl Contract {
       nft_contract_id: AccountId,
       template_id: String,
       price: U128,
-      token_id: TokenId,
       issued_at: Option<u64>,
       perpetual_royalties: Option<HashMap<AccountId, u16>>,  // temporarily. 
-    ) {
+      receiver_id: Option<AccountId>,
+      memo: Option<String>,
+      merkle_proof: Option<Vec<Base64VecU8>>,
+      promo_code: Option<String>,
+      tier: Option<String>,
+    ) -> MintedTicket {
       require!(
         env::attached_deposit() >= (u128::from(price) + near_to_yoctonear(0.1)),
         concat!(
@@ -159,60 +223,50 @@ impl Contract {
         )
       );
 
-      require!(
-        env::attached_deposit() <= (u128::from(price) + near_to_yoctonear(0.101)),
-        "You attached too much near. This function requires EXACTLY price + 0.1N."
-      );
+      // payer may be a contract (DAO, multisig) buying on behalf of users. 
+      let payer_id = env::predecessor_account_id();
 
-      let max_num_of_mint = expect_lightweight(
-        self.max_mint.get(&template_id),
-        "Cannot find template id. Ensure template_id is correct or created!"
+      // for tiered, presale and Dutch auction templates, price is the most you're willing to pay. 
+      let price = self.internal_purchase_price(
+        &template_id, &payer_id, merkle_proof.as_ref(), tier.as_ref(), promo_code.as_ref(), 1, None, price
       );
 
-      let mut minted = match self.minted.get(&template_id) {
-        Some(value) => value,
-        None => 0
-      };
-
-      require!(
-        minted <= max_num_of_mint,
-        "This template has reached its max minting number. Cannot mint anymore."
+      // anything attached above the price and storage reserve is refunded. 
+      let refund = self.internal_refund_surplus(
+        price.0 + near_to_yoctonear(0.1),
+        payer_id.clone()
       );
 
-      minted += 1;
-      self.minted.insert(&template_id, &minted);
-
-      let nft_seller_id = expect_lightweight(
-        self.template_owner.get(&template_id),
-        "Cannot find template owner. Ensure template_id is correct or created!"
+      // unsafe as revenue is credited irregardless of success or fail mint. 
+      let promo_code_hash = promo_code.as_deref().map(hash_promo_code);
+      let initial_storage_usage = env::storage_usage();
+      let mut mint = self.internal_prepare_mint(
+        &nft_contract_id, &template_id, &payer_id, price, tier.as_ref(), promo_code_hash.as_ref(), None
       );
-
-      let mut metadata = expect_lightweight(
-        self.template_metadata.get(&template_id),
-        "Cannot find template metadata. Ensure template_id is correct or created!"
+      let mint_deposit = mint_deposit(1, initial_storage_usage);
+
+      mint.metadata.issued_at = issued_at;
+
+      ext_contract::nft_mint(
+        mint.token_id.clone(),
+        mint.metadata,
+        receiver_id.unwrap_or_else(|| payer_id.clone()),
+        perpetual_royalties,
+        mint.size,
+        Some(payer_id),
+        self.transfer_rules.get(&template_id),
+        Some(price),
+        memo,
+
+        nft_contract_id,
+        mint_deposit,
+        GAS_FOR_MINTING
       );
 
-      metadata.issued_at = issued_at;
-      metadata.copies = Some(minted);
-
-      let size = self.nft_size.get(&template_id);
-      
-      Promise::new(nft_seller_id).transfer(price.into()).then(
-        // unsafe as mint irregardless of success or fail transfer money. 
-
-        ext_contract::nft_mint(
-          token_id,
-          metadata,
-          env::signer_account_id(),
-          perpetual_royalties,
-          size,
-          Some(env::signer_account_id()),
-  
-          nft_contract_id,
-          near_to_yoctonear(0.1),
-          GAS_FOR_MINTING
-        )
-      );
+      MintedTicket {
+        token_id: mint.token_id,
+        refund: U128(refund),
+      }
     }
 
     /// removes a sale from the market
@@ -255,7 +309,49 @@ impl Contract {
         "Only sale owner can update price."
       );
 
-      sale.sale_conditions = price;
+      self.internal_assert_within_resale_cap(&contract_and_token_id, price.0, None);
+
+      sale.sale_conditions = Some(price);
+      self.sales.insert(&contract_and_token_id, &sale);
+    }
+
+    /// updates the price in a fungible token for a sale on the market. 
+    /// Pass in no price to stop accepting that fungible token. 
+    #[payable]
+    pub fn update_ft_price(
+      &mut self,
+      nft_contract_id: AccountId,
+      token_id: String,
+      ft_token_id: FungibleTokenId,
+      price: Option<U128>,
+    ) {
+      assert_one_yocto();
+
+      let contract_id: AccountId = nft_contract_id.into();
+      let contract_and_token_id = format!("{}{}{}", contract_id, DELIMITER, token_id);
+
+      let mut sale = expect_lightweight(
+        self.sales.get(&contract_and_token_id),
+        "No Sale"
+      );
+
+      require!(
+        env::predecessor_account_id() == sale.owner_id,
+        "Only sale owner can update price."
+      );
+
+      if let Some(price) = price {
+        require!(
+          self.ft_token_ids.contains(&ft_token_id),
+          "Fungible token is not accepted on this marketplace."
+        );
+
+        self.internal_assert_within_resale_cap(&contract_and_token_id, price.0, Some(&ft_token_id));
+        sale.ft_sale_conditions.insert(ft_token_id, price);
+      } else {
+        sale.ft_sale_conditions.remove(&ft_token_id);
+      }
+
       self.sales.insert(&contract_and_token_id, &sale);
     }
 
@@ -287,7 +383,12 @@ impl Contract {
         "Cannot bid on your own sale."
       );
 
-      let price = sale.sale_conditions.0; 
+      require!(!sale.is_expired(), "Sale has expired.");
+
+      let price = expect_lightweight(
+        sale.sale_conditions,
+        "This sale is not for sale in NEAR."
+      ).0;
 
       require!(
         deposit >= price,
@@ -303,43 +404,34 @@ impl Contract {
         token_id,
         U128(deposit),
         buyer_id,
+        None,
       );
     }
 
     // ================ PRIVATE FUNCTIONS ======================= //
     
     /// Remove the sale, transfer and get payout from nft contract,
-    /// then distribute royalties
+    /// then distribute royalties. Price is in the fungible token if given,
+    /// else in NEAR. 
     #[private]
     pub fn process_purchase(
       &mut self,
       nft_contract_id: AccountId,
       token_id: String,
       price: U128,
-      buyer_id: AccountId
+      buyer_id: AccountId,
+      ft_token_id: Option<FungibleTokenId>,
     ) -> Promise {
       let sale = self.internal_remove_sale(nft_contract_id.clone(), token_id.clone());
 
-      // initiate cross-contract call. 
-      // Transfer token to buyer and return payout object for distributing funds.
-      ext_contract::nft_transfer_payout(
-        buyer_id.clone(),
+      self.internal_transfer_payout(
+        nft_contract_id,
         token_id,
         sale.approval_id,
-        "payout from market".to_string(),  // memo
-        price,  // includes royalties
-        10,  // max amount of accounts market can payout
-        nft_contract_id,  // contract to initiate cross contract call to
-        1,  // attached yoctoNEAR
-        GAS_FOR_NFT_TRANSFER,
-      )
-      .then(ext_self::resolve_purchase(
-        buyer_id,
         price,
-        env::current_account_id(),  // invoking this function on current contract
-        NO_DEPOSIT,
-        GAS_FOR_ROYALTIES,
-      ))
+        buyer_id,
+        ft_token_id,
+      )
     }
 
     /// Resolve promise when calling nft_transfer_payout. Check for authenticity
@@ -349,41 +441,10 @@ impl Contract {
       &mut self,
       buyer_id: AccountId,
       price: U128,
+      protocol_fee: U128,
     ) -> U128 {
       // check payout info returned from nft_transfer_payout method. 
-      let payout_option = promise_result_as_success().and_then(|value| {
-
-        // if payout option None, something wrong, refund. 
-        near_sdk::serde_json::from_slice::<Payout>(&value)
-            .ok()
-            // returns None if none; otherwise execute logic below. 
-            .and_then(|payout_object| {  
-
-              if payout_object.payout.len() > 10 || payout_object.payout.is_empty() {
-                env::log_str("Either more than 10 royalties or nobody to payout.");
-                None
-              } else {
-
-                let mut remainder = price.0;
-
-                // loop through payout and subtract value from remainder.
-                // this check for overflow or any errors and returns None if
-                // there is problems. 
-                for &value in payout_object.payout.values() {
-                  remainder = remainder.checked_sub(value.0)?;
-                }
-
-                // check for faulty payout that requires us to pay more or too 
-                // little. Remainder 0 if payout summed to total. Remainder 1 if
-                // rounded off error, like 3333 + 3333 + 3333 = 10000 - 1, the 1. 
-                if remainder == 0 || remainder == 1 {
-                  Some(payout_object.payout)  // nothing wrong. 
-                } else {
-                  None  // other remainder means something wrong. 
-                }
-              }
-            })
-      });
+      let payout_option = payout_from_promise_result(price.0 - protocol_fee.0);
 
       let payout = if let Some(payout_option) = payout_option {
         payout_option
@@ -393,6 +454,8 @@ impl Contract {
         return price;  // leave function and return price refunded. 
       };
 
+      self.internal_credit_treasury(None, protocol_fee.0);
+
       // NEAR payouts
       for (receiver_id, amount) in payout {
         Promise::new(receiver_id).transfer(amount.0);
@@ -402,45 +465,37 @@ impl Contract {
     }
 
 
+    /// Resolve promise when calling nft_mint. If minting failed, reverse the
+    /// revenue credited, give back the promo code use and refund buyer the
+    /// price and storage deposit. 
     #[private]
-    #[payable]
-    pub fn on_nft_mint(
+    pub fn resolve_mint(
       &mut self,
-      payment_amount: U128,
       nft_contract_id: AccountId,
       template_id: String,
       token_id: TokenId,
-      receiver_id: AccountId,
-      perpetual_royalties: Option<HashMap<AccountId, u16>>,
-      refund_to_signer: Option<AccountId>,
-    ) -> Promise {
+      price: U128,
+      buyer_id: AccountId,
+      purchase_recorded: bool,
+      protocol_fee: U128,
+      tier: Option<String>,
+      promo_code_hash: Option<Base64VecU8>,
+      mint_deposit: U128,
+    ) -> bool {
       if is_promise_success() {
-  
-        let metadata = expect_lightweight(
-          self.template_metadata.get(&template_id),
-          "Cannot find template metadata. Ensure template_id is correct or created!"
-        );
-
-        // add issued at and mint in the future. 
-  
-        let size = self.nft_size.get(&template_id);
+        return true;
+      }
 
+      self.internal_revert_mint(
+        &nft_contract_id, &token_id, &template_id, &buyer_id, None, price.0, protocol_fee.0, purchase_recorded
+      );
+      self.internal_revert_tier(&template_id, tier.as_ref());
+      self.internal_restore_promo_code(&template_id, promo_code_hash.as_ref(), 1);
 
-        ext_contract::nft_mint(
-          token_id,
-          metadata,
-          receiver_id,
-          perpetual_royalties,
-          size,
-          refund_to_signer,
+      // storage deposit attached to nft_mint comes back to us on failure. 
+      Promise::new(buyer_id).transfer(price.0 + mint_deposit.0);
 
-          nft_contract_id,
-          near_to_yoctonear(0.1),
-          GAS_FOR_MINTING
-        )
-      } else {
-        Promise::new(env::signer_account_id()).transfer(payment_amount.into())
-      }
+      false
     }
 }
 
@@ -452,16 +507,98 @@ trait ExtSelf {
     &mut self,
     buyer_id: AccountId,
     price: U128,
+    protocol_fee: U128,
   ) -> Promise;
 
-  fn on_nft_mint(
+  fn resolve_mint(
+    &mut self,
+    nft_contract_id: AccountId,
+    template_id: String,
+    token_id: TokenId,
+    price: U128,
+    buyer_id: AccountId,
+    purchase_recorded: bool,
+    protocol_fee: U128,
+    tier: Option<String>,
+    promo_code_hash: Option<Base64VecU8>,
+    mint_deposit: U128,
+  ) -> bool;
+
+  fn resolve_mint_batch(
     &mut self,
-    payment_amount: U128,
     nft_contract_id: AccountId,
     template_id: String,
+    token_ids: Vec<TokenId>,
+    price: U128,
+    buyer_id: AccountId,
+    purchases_recorded: Vec<bool>,
+    protocol_fee: U128,
+    tier: Option<String>,
+    promo_code_hash: Option<Base64VecU8>,
+    mint_deposit: U128,
+  ) -> bool;
+
+  fn resolve_refund(
+    &mut self,
+    nft_contract_id: AccountId,
     token_id: TokenId,
+    template_id: String,
+    amount: U128,
+    ft_token_id: Option<FungibleTokenId>,
+    protocol_fee: U128,
+  ) -> U128;
+
+  fn resolve_ft_payment(
+    &mut self,
     receiver_id: AccountId,
-    perpetual_royalties: Option<HashMap<AccountId, u16>>,
-    refund_to_signer: Option<AccountId>,
-  ) -> Promise;
+    ft_token_id: FungibleTokenId,
+    amount: U128,
+  ) -> bool;
+
+  fn resolve_bundle_purchase(
+    &mut self,
+    bundle_id: String,
+    owner_id: AccountId,
+    tokens: Vec<BundleToken>,
+    leg_amounts: Vec<U128>,
+    buyer_id: AccountId,
+    price: U128,
+    protocol_fee: U128,
+  ) -> U128;
+
+  fn resolve_bundle_delivery(
+    &mut self,
+    bundle_id: String,
+    delivery: BundleDelivery,
+  ) -> bool;
+
+  fn resolve_prune_sale(
+    &mut self,
+    nft_contract_id: AccountId,
+    token_id: TokenId,
+    approval_id: u64,
+  ) -> bool;
+
+  fn resolve_ft_purchase(
+    &mut self,
+    buyer_id: AccountId,
+    ft_token_id: FungibleTokenId,
+    price: U128,
+    protocol_fee: U128,
+  ) -> U128;
+
+  fn resolve_ft_mint(
+    &mut self,
+    nft_contract_id: AccountId,
+    template_id: String,
+    token_id: TokenId,
+    buyer_id: AccountId,
+    ft_token_id: FungibleTokenId,
+    amount: U128,
+    price: U128,
+    purchase_recorded: bool,
+    protocol_fee: U128,
+    promo_code_hash: Option<Base64VecU8>,
+    mint_deposit: U128,
+  ) -> U128;
 }
@@ -35,6 +35,19 @@ impl Contract {
       }
     }
 
+    /// Get how many more tickets an account can buy from a template. 
+    /// None if the template doesn't limit purchases per account. 
+    pub fn get_remaining_allowance(
+      &self,
+      template_id: String,
+      account_id: AccountId,
+    ) -> Option<U64> {
+      let max_per_account = self.template_max_per_account.get(&template_id)?;
+      let bought = self.purchases_per_account.get(&(template_id, account_id)).unwrap_or(0);
+
+      Some(U64(max_per_account.saturating_sub(bought)))
+    }
+
     /// returns the number of sales for a given account
     /// (result is a string)
     pub fn get_supply_by_owner_id(&self, account_id: AccountId) -> U64 {
@@ -67,9 +80,10 @@ impl Contract {
       let start = u128::from(from_index.unwrap_or(U128(0)));
 
       keys.iter()
+          .map(|token_id| self.sales.get(&token_id).unwrap())
+          .filter(|sale| !sale.is_expired())  // hide expired sales. 
           .skip(start as usize)
           .take(limit.unwrap_or(10) as usize)  // limit makes sense to have 10, not 0. 
-          .map(|token_id| self.sales.get(&token_id).unwrap())
           .collect()
     }
 
@@ -109,17 +123,24 @@ impl Contract {
       let start = u128::from(from_index.unwrap_or(U128(0)));
 
       keys.iter()
-          .skip(start as usize)
-          .take(limit.unwrap_or(10) as usize)
           .map(|token_id| self.sales.get(&format!(
             "{}{}{}", nft_contract_id, DELIMITER, token_id
           )).unwrap())
+          .filter(|sale| !sale.is_expired())
+          .skip(start as usize)
+          .take(limit.unwrap_or(10) as usize)
           .collect()
     }
 
     /// get a sale information for a given unique sale ID
-    /// (contract + DELIMITER + token ID)
+    /// (contract + DELIMITER + token ID). None if expired. 
     pub fn get_sale(&self, nft_contract_id: ContractAndTokenId) -> Option<Sale> {
-      self.sales.get(&nft_contract_id)
+      self.sales.get(&nft_contract_id).filter(|sale| !sale.is_expired())
+    }
+
+    /// get template and original purchase price of a ticket minted here,
+    /// for a given unique sale ID (contract + DELIMITER + token ID)
+    pub fn get_purchase(&self, nft_contract_id: ContractAndTokenId) -> Option<Purchase> {
+      self.purchases.get(&nft_contract_id)
     }
-}
\ No newline at end of file
+}
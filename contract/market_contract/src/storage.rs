@@ -0,0 +1,216 @@
+use crate::*;
+
+// Storage management (NEP-145). Every sale, auction, offer and bundle
+// takes up one slot of STORAGE_PER_SALE paid from the account's balance.
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalance {
+    pub total: U128,
+    pub available: U128,  // not used by any slot, can be withdrawn.
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalanceBounds {
+    pub min: U128,
+    pub max: Option<U128>,  // None as there's no limit on slots.
+}
+
+
+#[near_bindgen]
+impl Contract {
+    /// Allow users to deposit storage. This cover cost of storing sale objects
+    /// on the contract. Optional account ID for users to pay for storage for
+    /// other people. With registration_only, only the minimum is kept for
+    /// a new account and the rest refunded.
+    #[payable]
+    pub fn storage_deposit(
+      &mut self,
+      account_id: Option<AccountId>,
+      registration_only: Option<bool>,
+    ) -> StorageBalance {
+      let storage_account_id = account_id
+              .map(|a| a.into())
+              // if no specify account_id, use caller of function
+              .unwrap_or_else(env::predecessor_account_id);
+
+      let deposit = env::attached_deposit();
+      let balance = self.storage_deposits.get(&storage_account_id);
+
+      if registration_only.unwrap_or(false) {
+        // already registered: nothing to do, refund everything.
+        if balance.is_some() {
+          if deposit > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(deposit);
+          }
+          return self.internal_storage_balance(&storage_account_id).unwrap();
+        }
+
+        require!(
+          deposit >= STORAGE_PER_SALE,
+          format!("Requires minimum deposit of {}", STORAGE_PER_SALE),
+        );
+
+        self.storage_deposits.insert(&storage_account_id, &STORAGE_PER_SALE);
+
+        let refund = deposit - STORAGE_PER_SALE;
+        if refund > 0 {
+          Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+      } else {
+        // new accounts need at least enough for one slot.
+        if balance.is_none() {
+          require!(
+            deposit >= STORAGE_PER_SALE,
+            format!("Requires minimum deposit of {}", STORAGE_PER_SALE),
+          );
+        }
+
+        self.storage_deposits.insert(
+          &storage_account_id,
+          &(balance.unwrap_or(0) + deposit)
+        );
+      }
+
+      self.internal_storage_balance(&storage_account_id).unwrap()
+    }
+
+
+    /// Allow users to withdraw any excess storage that they're not using.
+    /// Withdraws all available balance if no amount given.
+    #[payable]
+    pub fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+      assert_one_yocto();
+
+      let owner_id = env::predecessor_account_id();
+
+      let storage_balance = expect_lightweight(
+        self.internal_storage_balance(&owner_id),
+        "Account is not registered."
+      );
+
+      let amount = amount.map(|amount| amount.0).unwrap_or(storage_balance.available.0);
+
+      require!(
+        amount <= storage_balance.available.0,
+        format!(
+          "Cannot withdraw {}, only {} is not used by sales, auctions, offers or bundles.",
+          amount,
+          storage_balance.available.0
+        )
+      );
+
+      if amount > 0 {
+        self.storage_deposits.insert(&owner_id, &(storage_balance.total.0 - amount));
+        Promise::new(owner_id.clone()).transfer(amount);
+      }
+
+      self.internal_storage_balance(&owner_id).unwrap()
+    }
+
+
+    /// Remove the account and refund its whole balance. Fails if the account
+    /// has any slots in use, unless force is given: then its sales, bundles
+    /// and auctions without bids are removed. Offers and auctions with bids
+    /// hold funds and must be settled first either way. Returns false if the
+    /// account wasn't registered.
+    #[payable]
+    pub fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+      assert_one_yocto();
+
+      let owner_id = env::predecessor_account_id();
+
+      if !self.storage_deposits.contains_key(&owner_id) {
+        return false;
+      }
+
+      if self.internal_storage_slots_used(&owner_id) > 0 {
+        require!(
+          force.unwrap_or(false),
+          "Account still has sales, auctions, offers or bundles. Remove them or use force."
+        );
+
+        self.internal_remove_listings_of(&owner_id);
+
+        require!(
+          self.internal_storage_slots_used(&owner_id) == 0,
+          "Withdraw offers and settle auctions with bids before unregistering."
+        );
+      }
+
+      let balance = self.storage_deposits.remove(&owner_id).unwrap_or(0);
+      if balance > 0 {
+        Promise::new(owner_id).transfer(balance);
+      }
+
+      true
+    }
+
+    // views
+    /// return the minimum storage for 1 sale
+    pub fn storage_minimum_balance(&self) -> U128 {
+      U128(STORAGE_PER_SALE)
+    }
+
+    /// minimum balance to register, and no maximum.
+    pub fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+      StorageBalanceBounds {
+        min: U128(STORAGE_PER_SALE),
+        max: None,
+      }
+    }
+
+    /// returns how much storage an account has paid for and how much of it
+    /// is free. None if not registered.
+    pub fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+      self.internal_storage_balance(&account_id)
+    }
+}
+
+
+impl Contract {
+    /// storage balance of an account, with one STORAGE_PER_SALE locked for
+    /// every slot it uses.
+    pub(crate) fn internal_storage_balance(&self, account_id: &AccountId) -> Option<StorageBalance> {
+      let total = self.storage_deposits.get(account_id)?;
+      let used = self.internal_storage_slots_used(account_id) as u128 * STORAGE_PER_SALE;
+
+      Some(StorageBalance {
+        total: U128(total),
+        available: U128(total.saturating_sub(used)),
+      })
+    }
+
+    /// remove an account's sales, bundles and auctions nobody has bid on.
+    pub(crate) fn internal_remove_listings_of(&mut self, owner_id: &AccountId) {
+      let sales: Vec<Sale> = self.by_owner_id.get(owner_id)
+          .map(|by_owner_id| by_owner_id.iter()
+            .map(|contract_and_token_id| self.sales.get(&contract_and_token_id).unwrap())
+            .collect())
+          .unwrap_or_default();
+
+      for sale in sales {
+        self.internal_remove_sale(sale.nft_contract_id.parse().unwrap(), sale.token_id);
+      }
+
+      let bundle_ids: Vec<String> = self.bundles_by_owner_id.get(owner_id)
+          .map(|by_owner_id| by_owner_id.to_vec())
+          .unwrap_or_default();
+
+      for bundle_id in bundle_ids {
+        self.internal_remove_bundle(&bundle_id);
+      }
+
+      let auctions: Vec<Auction> = self.auctions_by_owner_id.get(owner_id)
+          .map(|by_owner_id| by_owner_id.iter()
+            .map(|contract_and_token_id| self.auctions.get(&contract_and_token_id).unwrap())
+            .filter(|auction| auction.bids.is_empty())
+            .collect())
+          .unwrap_or_default();
+
+      for auction in auctions {
+        self.internal_remove_auction(auction.nft_contract_id.parse().unwrap(), auction.token_id);
+      }
+    }
+}
@@ -0,0 +1,148 @@
+use crate::*;
+use near_sdk::serde_json::{self, Map, Value};
+
+/// Named price tier of a template, like adult, child or family. Tickets
+/// minted in a tier have its size (template size if None) and price. A
+/// tier's max_mint caps its tickets on top of the template's max_mint.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriceTier {
+    pub price: U128,
+    pub size: Option<usize>,
+    pub max_mint: Option<u64>,
+}
+
+// max number of tiers on a template.
+const MAX_TIERS: usize = 10;
+
+
+#[near_bindgen]
+impl Contract {
+    /// Set price tiers of a template. Template owner only. Replaces tiers
+    /// previously set; tickets already minted in a tier still count against
+    /// its cap. Once a template has tiers, every purchase picks one. Tiers
+    /// can't be combined with a Dutch auction, and are bought in NEAR only.
+    #[payable]
+    pub fn set_template_tiers(&mut self, template_id: String, tiers: HashMap<String, PriceTier>) {
+      let initial_storage_usage = env::storage_usage();
+
+      self.internal_assert_template_owner(&template_id);
+
+      require!(
+        self.template_dutch_auction.get(&template_id).is_none(),
+        "Dutch auction templates cannot have tiers."
+      );
+
+      require!(
+        tiers.len() <= MAX_TIERS,
+        format!("A template cannot have more than {} tiers.", MAX_TIERS)
+      );
+
+      let max_num_of_mint = self.max_mint.get(&template_id).unwrap_or(0);
+      for (tier, price_tier) in tiers.iter() {
+        require!(
+          price_tier.size.map(|size| size > 0).unwrap_or(true),
+          format!("Tier {} must have a size of at least 1.", tier)
+        );
+
+        require!(
+          price_tier.max_mint.map(|max_mint| max_mint <= max_num_of_mint).unwrap_or(true),
+          format!("Tier {} cannot mint more than the template's {} tickets.", tier, max_num_of_mint)
+        );
+      }
+
+      if tiers.is_empty() {
+        self.template_tiers.remove(&template_id);
+      } else {
+        self.template_tiers.insert(&template_id, &tiers);
+      }
+
+      let required_storage_in_bytes = env::storage_usage().saturating_sub(initial_storage_usage);
+      self.refund_deposit(required_storage_in_bytes, env::predecessor_account_id());
+    }
+
+    // views
+    /// returns price tiers of a template, empty if it has none.
+    pub fn get_template_tiers(&self, template_id: String) -> HashMap<String, PriceTier> {
+      self.template_tiers.get(&template_id).unwrap_or_default()
+    }
+
+    /// Get number of tickets minted in a tier of a template.
+    pub fn get_tier_minted(&self, template_id: String, tier: String) -> u64 {
+      self.tier_minted.get(&(template_id, tier)).unwrap_or(0)
+    }
+}
+
+
+impl Contract {
+    /// The tier bought from a template. Panics if the template has tiers and
+    /// none, or an unknown one, is given; or a tier is given for a template
+    /// without tiers.
+    pub(crate) fn internal_price_tier(&self, template_id: &String, tier: Option<&String>) -> Option<PriceTier> {
+      let tiers = match self.template_tiers.get(template_id) {
+        Some(tiers) => tiers,
+        None => {
+          require!(tier.is_none(), "This template has no tiers.");
+          return None;
+        }
+      };
+
+      let tier = expect_lightweight(tier, "This template has tiers. Pass in the tier to buy.");
+
+      Some(expect_lightweight(
+        tiers.get(tier).cloned(),
+        "Cannot find tier. Ensure tier is one of the template's tiers!"
+      ))
+    }
+
+    /// Panic if a tier reached its cap, and take up one of its slots. 
+    /// Nothing to do without a tier.
+    pub(crate) fn internal_reserve_tier(&mut self, template_id: &String, tier: Option<&String>) {
+      let tier = if let Some(tier) = tier { tier } else { return };
+      let price_tier = expect_lightweight(
+        self.internal_price_tier(template_id, Some(tier)),
+        "Cannot find tier. Ensure tier is one of the template's tiers!"
+      );
+
+      let template_and_tier = (template_id.clone(), tier.clone());
+      let minted = self.tier_minted.get(&template_and_tier).unwrap_or(0);
+
+      if let Some(max_mint) = price_tier.max_mint {
+        require!(
+          minted < max_mint,
+          format!("Tier {} has reached its max minting number. Cannot mint anymore.", tier)
+        );
+      }
+
+      self.tier_minted.insert(&template_and_tier, &(minted + 1));
+    }
+
+    /// Give back a tier slot after minting failed or the ticket was refunded.
+    /// Nothing to do without a tier.
+    pub(crate) fn internal_revert_tier(&mut self, template_id: &String, tier: Option<&String>) {
+      let tier = if let Some(tier) = tier { tier } else { return };
+      let template_and_tier = (template_id.clone(), tier.clone());
+      if let Some(minted) = self.tier_minted.get(&template_and_tier) {
+        self.tier_minted.insert(&template_and_tier, &minted.saturating_sub(1));
+      }
+    }
+}
+
+
+/// Record the tier in metadata `extra`, as a `tier` field of its JSON
+/// object. Extra that isn't a JSON object is kept under an `extra` field.
+pub(crate) fn metadata_with_tier(mut metadata: TokenMetadata, tier: &str) -> TokenMetadata {
+  let mut extra = match metadata.extra.as_deref().map(serde_json::from_str::<Value>) {
+    Some(Ok(Value::Object(extra))) => extra,
+    Some(_) => {
+      let mut extra = Map::new();
+      extra.insert("extra".to_string(), Value::String(metadata.extra.clone().unwrap()));
+      extra
+    },
+    None => Map::new(),
+  };
+
+  extra.insert("tier".to_string(), Value::String(tier.to_string()));
+  metadata.extra = Some(Value::Object(extra).to_string());
+  metadata
+}
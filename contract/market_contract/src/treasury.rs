@@ -0,0 +1,135 @@
+use crate::*;
+
+/// Returned from `get_protocol_fees` view call. 
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProtocolFees {
+    pub primary_fee_bps: u16,
+    pub secondary_fee_bps: u16,
+    pub treasury_balance: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Set protocol fees in basis points (100 = 1%). Contract owner only. 
+    pub fn set_protocol_fees(&mut self, primary_fee_bps: u16, secondary_fee_bps: u16) {
+      require!(
+        env::predecessor_account_id() == self.owner_id,
+        "Only contract owner can set protocol fees."
+      );
+
+      require!(
+        primary_fee_bps <= 10_000 && secondary_fee_bps <= 10_000,
+        "Protocol fee cannot be more than 10000 basis points."
+      );
+
+      self.primary_fee_bps = primary_fee_bps;
+      self.secondary_fee_bps = secondary_fee_bps;
+    }
+
+    /// Owner withdraws collected protocol fees. Withdraws everything if 
+    /// no amount is passed in. Pass in ft_token_id to withdraw fees 
+    /// collected in that fungible token instead of NEAR. 
+    #[payable]
+    pub fn withdraw_treasury(
+      &mut self, 
+      amount: Option<U128>,
+      ft_token_id: Option<FungibleTokenId>,
+    ) -> U128 {
+      assert_one_yocto();
+
+      require!(
+        env::predecessor_account_id() == self.owner_id,
+        "Only contract owner can withdraw treasury."
+      );
+
+      let balance = self.internal_treasury_balance(ft_token_id.as_ref());
+      let amount = amount.map(|a| a.0).unwrap_or(balance);
+
+      require!(
+        amount <= balance,
+        format!("Cannot withdraw {}, treasury only holds {}.", amount, balance)
+      );
+
+      self.internal_debit_treasury(ft_token_id.as_ref(), amount);
+
+      if amount > 0 {
+        self.internal_pay(self.owner_id.clone(), ft_token_id, amount);
+      }
+
+      U128(amount)
+    }
+
+    // views
+    /// returns protocol fees and treasury balance. 
+    pub fn get_protocol_fees(&self) -> ProtocolFees {
+      ProtocolFees {
+        primary_fee_bps: self.primary_fee_bps,
+        secondary_fee_bps: self.secondary_fee_bps,
+        treasury_balance: U128(self.treasury_balance),
+      }
+    }
+
+    /// returns protocol fees collected in a fungible token. 
+    pub fn get_ft_treasury_balance(&self, ft_token_id: FungibleTokenId) -> U128 {
+      U128(self.internal_treasury_balance(Some(&ft_token_id)))
+    }
+}
+
+impl Contract {
+    /// protocol fee for a given amount at the given rate. 
+    pub(crate) fn internal_protocol_fee(&self, amount: Balance, fee_bps: u16) -> Balance {
+      amount * fee_bps as u128 / 10_000u128
+    }
+
+    /// treasury balance in NEAR, or in a fungible token. 
+    pub(crate) fn internal_treasury_balance(&self, ft_token_id: Option<&FungibleTokenId>) -> Balance {
+      if let Some(ft_token_id) = ft_token_id {
+        self.ft_treasury_balances.get(ft_token_id).unwrap_or(0)
+      } else {
+        self.treasury_balance
+      }
+    }
+
+    pub(crate) fn internal_credit_treasury(
+      &mut self, 
+      ft_token_id: Option<&FungibleTokenId>, 
+      amount: Balance
+    ) {
+      if let Some(ft_token_id) = ft_token_id {
+        let balance = self.ft_treasury_balances.get(ft_token_id).unwrap_or(0);
+        self.ft_treasury_balances.insert(ft_token_id, &(balance + amount));
+      } else {
+        self.treasury_balance += amount;
+      }
+    }
+
+    pub(crate) fn internal_debit_treasury(
+      &mut self, 
+      ft_token_id: Option<&FungibleTokenId>, 
+      amount: Balance
+    ) {
+      if let Some(ft_token_id) = ft_token_id {
+        let balance = self.ft_treasury_balances.get(ft_token_id).unwrap_or(0);
+        self.ft_treasury_balances.insert(ft_token_id, &balance.saturating_sub(amount));
+      } else {
+        self.treasury_balance = self.treasury_balance.saturating_sub(amount);
+      }
+    }
+
+    /// split a primary sale between treasury and the template's escrow. 
+    /// Returns the protocol fee taken. 
+    pub(crate) fn internal_credit_primary_sale(
+      &mut self, 
+      template_id: &String, 
+      ft_token_id: Option<&FungibleTokenId>,
+      price: Balance
+    ) -> Balance {
+      let protocol_fee = self.internal_protocol_fee(price, self.primary_fee_bps);
+
+      self.internal_credit_treasury(ft_token_id, protocol_fee);
+      self.internal_credit_revenue(template_id, ft_token_id, price - protocol_fee);
+
+      protocol_fee
+    }
+}
@@ -2,28 +2,27 @@
 
 export MARKET_CONTRACT=zoo_marketplace.wabinab.testnet
 export NFT_CONTRACT=zoo_nft.wabinab.testnet
-export SELLER_ID=somebodyelse.testnet  # another of my account. 
-export TOKEN_ID="zoo_movie_ticket_for_four"
+export TEMPLATE_ID="zoo_movie_ticket_for_four"
 
+# Template must be created first with generate_template, priced at 1 NEAR. 
 # Simulate NFT cost 1 NEAR, so total paying 1.1 NEAR (plus storage). 
 # We don't add perpetual royalties for simplicity. 
 # Receiver ID is signer, so that's eliminated. 
-# size is 4, since ticket for 4. 
 # Attached gas is 70 TGas just in case. 
-# Must attach about exact, accurate to 0.001 N. 
+# Token ID is generated by the market, and returned in the MintedTicket. 
 
-near call $MARKET_CONTRACT pay_and_mint '{
+# Pull token_id out of the MintedTicket near-cli prints last. 
+minted_token_id() {
+  tail -n 1 | sed -n "s/.*token_id: '\([^']*\)'.*/\1/p"
+}
+
+export TOKEN_ID=$(near call $MARKET_CONTRACT pay_and_mint '{
   "nft_contract_id": "'$NFT_CONTRACT'",
-  "price": "1000000000000000000000000",
-  "nft_seller_id": "'$SELLER_ID'",
-  "token_id": "'$TOKEN_ID'",
-  "metadata": {
-    "title": "Zoo Movie Ticket",
-    "description": "Some description",
-    "media": "https://www.google.com"
-  },
-  "size": 4
-}' --accountId wabinab.testnet --gas=70000000000000 --amount=1.1
+  "template_id": "'$TEMPLATE_ID'",
+  "price": "1000000000000000000000000"
+}' --accountId wabinab.testnet --gas=70000000000000 --amount=1.1 | minted_token_id)
+
+echo "Minted $TOKEN_ID"
 
 
 # Check for minted NFT. 
@@ -34,25 +33,18 @@ near view $NFT_CONTRACT nft_token '{
 # ========================================================================
 
 # Check explorer for how much gas is saved with unsafe version. 
-# Unsafe MUST attach exact amount. 
-
-export TOKEN_ID="zoo_movie_ticket_for_four_unsafe"
+# Deposit above price + 0.1N storage reserve is refunded, as with pay_and_mint. 
 
-near call $MARKET_CONTRACT pay_and_mint_unsafe '{
+export TOKEN_ID=$(near call $MARKET_CONTRACT pay_and_mint_unsafe '{
   "nft_contract_id": "'$NFT_CONTRACT'",
-  "price": "1000000000000000000000000",
-  "nft_seller_id": "'$SELLER_ID'",
-  "token_id": "'$TOKEN_ID'",
-  "metadata": {
-    "title": "Zoo Movie Ticket",
-    "description": "Some description",
-    "media": "https://www.google.com"
-  },
-  "size": 4
-}' --accountId wabinab.testnet --gas=70000000000000 --amount=1.1
+  "template_id": "'$TEMPLATE_ID'",
+  "price": "1000000000000000000000000"
+}' --accountId wabinab.testnet --gas=70000000000000 --amount=1.1 | minted_token_id)
+
+echo "Minted $TOKEN_ID"
 
 
 # Check for minted NFT. 
 near view $NFT_CONTRACT nft_token '{
   "token_id": "'$TOKEN_ID'"
-}'
\ No newline at end of file
+}'
@@ -77,8 +77,10 @@ impl NonFungibleTokenCore for Contract {
         // insert token back to tokens_by_id collection
         self.tokens_by_id.insert(&token_id, &token);
     
-        // refund excess storage attached by user. If user didn't attach enough, panic. 
-        refund_deposit(storage_used, env::predecessor_account_id());
+        // refund excess storage attached by user. If user didn't attach enough,
+        // take the rest from their storage balance, or panic. 
+        let owner_id = env::predecessor_account_id();
+        self.internal_charge_storage(storage_used, &owner_id, owner_id.clone());
 
         // if message passed in, we initiate cross contract call on account we're 
         // giving access to. 
@@ -140,7 +142,7 @@ impl NonFungibleTokenCore for Contract {
             .is_some()
         {
           // removing approved_account_id and refund funds. 
-          refund_approved_account_ids_iter(predecessor_account_id, [account_id].iter());
+          self.refund_approved_account_ids_iter(predecessor_account_id, [account_id].iter());
 
           // insert token back to collection with removed account_id from approval list. 
           self.tokens_by_id.insert(&token_id, &token);
@@ -159,7 +161,7 @@ impl NonFungibleTokenCore for Contract {
 
         // only revoke for token not empty approved account IDs. 
         if !token.approved_account_ids.is_empty() {
-          refund_approved_account_ids(predecessor_account_id, &token.approved_account_ids);
+          self.refund_approved_account_ids(predecessor_account_id, &token.approved_account_ids);
           token.approved_account_ids.clear();
           self.tokens_by_id.insert(&token_id, &token);
         }
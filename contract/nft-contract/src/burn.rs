@@ -0,0 +1,67 @@
+use crate::*;
+use near_sdk::require;
+
+#[near_bindgen]
+impl Contract {
+    /// Burn a token. Callable by the token owner, or by the account that
+    /// minted it (e.g. the marketplace, when refunding a ticket). If owner_id
+    /// is passed in, the token must still belong to that account. Storage
+    /// freed is credited to the token owner's storage balance. Returns the burnt token's owner. 
+    #[payable]
+    pub fn nft_burn(
+      &mut self,
+      token_id: TokenId,
+      owner_id: Option<AccountId>,
+    ) -> AccountId {
+      assert_one_yocto();
+
+      let token = expect_lightweight(
+        self.tokens_by_id.get(&token_id),
+        "No token"
+      );
+
+      if let Some(owner_id) = owner_id {
+        require!(
+          token.owner_id == owner_id,
+          "Token is not owned by the given owner_id."
+        );
+      }
+
+      let predecessor_account_id = env::predecessor_account_id();
+      let minter_id = self.minted_by.get(&token_id);
+
+      require!(
+        predecessor_account_id == token.owner_id 
+            || Some(&predecessor_account_id) == minter_id.as_ref(),
+        "Only token owner or minter can burn the token."
+      );
+
+      let initial_storage_usage = env::storage_usage();
+
+      self.internal_burn(&token_id);
+
+      let storage_freed = initial_storage_usage - env::storage_usage();
+      self.internal_credit_storage(storage_freed, &token.owner_id);
+
+      // Log the burn as per events standard. 
+      let mut authorized_id = None;
+      if predecessor_account_id != token.owner_id {
+        authorized_id = Some(predecessor_account_id.to_string());
+      }
+
+      let nft_burn_log: EventLog = EventLog {
+        standard: NFT_STANDARD_NAME.to_string(),
+        version : NFT_METADATA_SPEC.to_string(),
+        event   : EventLogVariant::NftBurn(vec![NftBurnLog {
+          owner_id : token.owner_id.to_string(),
+          authorized_id,
+          token_ids: vec![token_id.to_string()],
+          memo     : None,
+        }]),
+      };
+
+      env::log_str(&nft_burn_log.to_string());
+
+      token.owner_id
+    }
+}
@@ -0,0 +1,219 @@
+use crate::*;
+use std::mem::size_of;
+
+// every storage record costs its key and value plus this many bytes.
+const STORAGE_BYTES_PER_RECORD: u64 = 40;
+
+
+/// bytes taken up by a storage record with the given key and value length.
+fn record_bytes(key: &[u8], value_len: usize) -> u64 {
+  key.len() as u64 + value_len as u64 + STORAGE_BYTES_PER_RECORD
+}
+
+/// borsh serialized length of a value
+fn borsh_len<T: BorshSerialize>(value: &T) -> usize {
+  value.try_to_vec().unwrap().len()
+}
+
+/// key of an entry in a collection with the given prefix.
+fn prefixed_key<K: BorshSerialize>(prefix: StorageKey, key: &K) -> Vec<u8> {
+  let mut prefixed_key = prefix.try_to_vec().unwrap();
+  prefixed_key.extend(key.try_to_vec().unwrap());
+  prefixed_key
+}
+
+
+#[near_bindgen]
+impl Contract {
+    /// Exact deposit nft_mint needs for the given arguments. minter_id is the
+    /// account that will call nft_mint (e.g. the marketplace), recorded as
+    /// the minter. Whatever isn't attached is taken from its storage balance.
+    pub fn estimate_mint_deposit(
+      &self,
+      token_id: TokenId,
+      metadata: TokenMetadata,
+      receiver_id: AccountId,
+      perpetual_royalties: Option<HashMap<AccountId, u16>>,
+      size: Option<usize>,
+      transfer_rules: Option<TransferRules>,
+      purchase_price: Option<U128>,
+      minter_id: AccountId,
+    ) -> U128 {
+      let token_id_raw = token_id.try_to_vec().unwrap();
+
+      let token = Token {
+        owner_id: receiver_id.clone(),
+        approved_account_ids: Default::default(),
+        next_approval_id: 0,
+        royalty: perpetual_royalties.unwrap_or_default(),
+      };
+
+      let mut bytes = record_bytes(
+        &prefixed_key(StorageKey::TokensById, &token_id),
+        borsh_len(&token)
+      );
+
+      // token_metadata_by_id keeps an index, a key and a value record.
+      let metadata_prefix = StorageKey::TokenMetadataById.try_to_vec().unwrap();
+      bytes += record_bytes(&[&metadata_prefix[..], b"i", &token_id_raw].concat(), size_of::<u64>());
+      bytes += record_bytes(&[&metadata_prefix[..], b"k", &0u64.to_le_bytes()].concat(), token_id_raw.len());
+      bytes += record_bytes(&[&metadata_prefix[..], b"v", &0u64.to_le_bytes()].concat(), borsh_len(&metadata));
+
+      bytes += self.internal_owner_set_insert_bytes(&receiver_id, &token_id);
+
+      if let Some(size) = size {
+        bytes += record_bytes(
+          &prefixed_key(StorageKey::ShareNFTs, &token_id),
+          borsh_len(&vec![receiver_id.clone(); size.saturating_sub(1)])
+        );
+      }
+
+      bytes += record_bytes(
+        &prefixed_key(StorageKey::TicketUsed, &token_id),
+        borsh_len(&vec![false; size.unwrap_or(1)])
+      );
+
+      if let Some(transfer_rules) = transfer_rules {
+        bytes += record_bytes(
+          &prefixed_key(StorageKey::TransferRules, &token_id),
+          borsh_len(&transfer_rules)
+        );
+      }
+
+      bytes += record_bytes(
+        &prefixed_key(StorageKey::MintedBy, &token_id),
+        borsh_len(&minter_id)
+      );
+
+      if purchase_price.is_some() {
+        bytes += record_bytes(&prefixed_key(StorageKey::PurchasePrice, &token_id), size_of::<Balance>());
+      }
+
+      U128(Balance::from(bytes) * env::storage_byte_cost())
+    }
+
+    /// Exact deposit set_accounts needs for the given share accounts. Zero
+    /// if storage is freed (it's credited to the owner's storage balance).
+    pub fn estimate_set_accounts_deposit(
+      &self,
+      token_id: TokenId,
+      share_accounts: Vec<AccountId>,
+    ) -> U128 {
+      let token = expect_lightweight(
+        self.tokens_by_id.get(&token_id),
+        "Cannot find token."
+      );
+
+      let old_share_accounts = expect_lightweight(
+        self.share_nfts.get(&token_id),
+        "Token is not sharable"
+      );
+
+      // same storage set_accounts measures: the share list and owner sets.
+      let mut total_storage = borsh_len(&share_accounts) as i64 - borsh_len(&old_share_accounts) as i64;
+
+      let old_owner_set: HashSet<AccountId> = old_share_accounts.into_iter().collect();
+      let new_owner_set: HashSet<AccountId> = share_accounts.into_iter().collect();
+
+      for account_id in old_owner_set.difference(&new_owner_set) {
+        if account_id != &token.owner_id {
+          total_storage -= self.internal_owner_set_remove_bytes(account_id, &token_id) as i64;
+        }
+      }
+
+      for account_id in new_owner_set.difference(&old_owner_set) {
+        if account_id != &token.owner_id {
+          total_storage += self.internal_owner_set_insert_bytes(account_id, &token_id) as i64;
+        }
+      }
+
+      U128(Balance::from(total_storage.max(0) as u64) * env::storage_byte_cost())
+    }
+
+    /// Exact deposit nft_approve needs to approve account_id for the token.
+    /// At least 1 yoctoNEAR.
+    pub fn estimate_approve_deposit(
+      &self,
+      token_id: TokenId,
+      account_id: AccountId,
+    ) -> U128 {
+      let token = expect_lightweight(
+        self.tokens_by_id.get(&token_id),
+        "No token"
+      );
+
+      if token.approved_account_ids.contains_key(&account_id) {
+        return U128(1);
+      }
+
+      let storage_cost = Balance::from(bytes_for_approved_account_id(&account_id))
+          * env::storage_byte_cost();
+
+      U128(storage_cost.max(1))
+    }
+}
+
+
+impl Contract {
+    /// bytes used adding a token to an account's set of tokens, including
+    /// the set itself if the account doesn't have one yet.
+    pub(crate) fn internal_owner_set_insert_bytes(
+      &self,
+      account_id: &AccountId,
+      token_id: &TokenId,
+    ) -> u64 {
+      let mut bytes = self.internal_owner_set_element_bytes(account_id, token_id);
+
+      if self.tokens_per_owner.get(account_id).is_none() {
+        let set_prefix_len = self.internal_owner_set_prefix(account_id).len() + 1;
+        // UnorderedSet: index prefix, elements vector length and prefix.
+        let set_len = (4 + set_prefix_len) + size_of::<u64>() + (4 + set_prefix_len);
+
+        bytes += record_bytes(&prefixed_key(StorageKey::TokensPerOwner, account_id), set_len);
+      }
+
+      bytes
+    }
+
+    /// bytes freed removing a token from an account's set of tokens,
+    /// including the set itself if it becomes empty.
+    pub(crate) fn internal_owner_set_remove_bytes(
+      &self,
+      account_id: &AccountId,
+      token_id: &TokenId,
+    ) -> u64 {
+      let tokens_set = match self.tokens_per_owner.get(account_id) {
+        Some(tokens_set) => tokens_set,
+        None => return 0,
+      };
+
+      let mut bytes = self.internal_owner_set_element_bytes(account_id, token_id);
+
+      if tokens_set.len() == 1 {
+        let set_prefix_len = self.internal_owner_set_prefix(account_id).len() + 1;
+        let set_len = (4 + set_prefix_len) + size_of::<u64>() + (4 + set_prefix_len);
+
+        bytes += record_bytes(&prefixed_key(StorageKey::TokensPerOwner, account_id), set_len);
+      }
+
+      bytes
+    }
+
+    /// prefix of an account's set of tokens.
+    fn internal_owner_set_prefix(&self, account_id: &AccountId) -> Vec<u8> {
+      StorageKey::TokenPerOwnerInner {
+        account_id_hash: hash_account_id(account_id),
+      }
+      .try_to_vec()
+      .unwrap()
+    }
+
+    /// bytes of the index and element records of one token in a set.
+    fn internal_owner_set_element_bytes(&self, account_id: &AccountId, token_id: &TokenId) -> u64 {
+      let set_prefix = self.internal_owner_set_prefix(account_id);
+      let token_id_raw = token_id.try_to_vec().unwrap();
+
+      record_bytes(&[&set_prefix[..], b"i", &token_id_raw].concat(), size_of::<u64>())
+        + record_bytes(&[&set_prefix[..], b"e", &0u64.to_le_bytes()].concat(), token_id_raw.len())
+    }
+}
@@ -3,7 +3,7 @@ use std::fmt;
 use near_sdk::serde::{Deserialize, Serialize};
 
 /// Enum that represetns the data type of the EventLog. 
-/// Can be either NftMint or NftTransfer
+/// Can be either NftMint, NftTransfer or NftBurn
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(tag="event", content="data")]
 #[serde(rename_all="snake_case")]
@@ -12,6 +12,7 @@ use near_sdk::serde::{Deserialize, Serialize};
 pub enum EventLogVariant {
   NftMint(Vec<NftMintLog>),
   NftTransfer(Vec<NftTransferLog>),
+  NftBurn(Vec<NftBurnLog>),
 }
 
 
@@ -82,3 +83,23 @@ pub struct NftTransferLog {
 }
 
 
+/// An event log to capture token burning
+/// 
+/// Arguments:
+///   owner_id: owner of the burnt token. 
+///   authorized_id: (optional) account approved to burn, if not the owner.
+///   token_ids: ["1", "12345abc"]
+///   memo: (optional) message.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftBurnLog {
+  pub owner_id: String,
+
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub authorized_id: Option<String>,
+
+  pub token_ids: Vec<String>,
+
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub memo: Option<String>,
+}
@@ -30,101 +30,98 @@ pub(crate) fn royalty_to_payout(royalty_percentage: u16, amount_to_pay: Balance)
 }
 
 
-/// refund storage taken up, passing in approved account IDs and send the funds
-/// to the passed-in account ID. 
-pub(crate) fn refund_approved_account_ids_iter<'a, I>(
-  account_id: AccountId,
-  approved_account_ids: I,  // approved account IDs must be passed in as iterator
-) -> Promise
-where
-  I: Iterator<Item = &'a AccountId>,
-{
-  // sum of all bytes for each approved account IDs. 
-  let storage_released: u64 = approved_account_ids.map(bytes_for_approved_account_id).sum();
-
-  // transfer to account storage cost equivalent to storage released. 
-  Promise::new(account_id).transfer(
-    Balance::from(storage_released) * env::storage_byte_cost()
-  )
+/// current block timestamp in milliseconds, same unit as the
+/// `starts_at`/`expires_at` fields of token metadata. 
+pub(crate) fn block_timestamp_ms() -> u64 {
+  env::block_timestamp() / 1_000_000
 }
 
 
-/// refund storage taken up to a given account id
-pub(crate) fn refund_storage_to_owner(
-  owner_id: AccountId,
-  storage_released: u64,
-) -> Promise {
-    Promise::new(owner_id).transfer(
-      Balance::from(storage_released) * env::storage_byte_cost()
-    )
+/// Assert user has attached at least 1 yoctoNEAr (for security reasons
+/// and payment of storage)
+pub(crate) fn assert_at_least_one_yocto() {
+  require!(
+    env::attached_deposit() >= 1,
+    "Requires attached deposit of at least 1 yoctoNEAR",
+  )
 }
 
 
-/// refund a map of approved account IDs and send the funds to the passed in 
-/// account ID
-pub(crate) fn refund_approved_account_ids(
-  account_id: AccountId,
-  approved_account_ids: &HashMap<AccountId, u64>,
-) -> Promise {
-  refund_approved_account_ids_iter(account_id, approved_account_ids.keys())
-}
+impl Contract {
+    /// Pay for storage used: from the attached deposit first, then from the
+    /// payer's prepaid storage balance. payer_id must be the caller, so
+    /// nobody spends another account's balance. Attached deposit not needed
+    /// is refunded to refund_id. 
+    pub(crate) fn internal_charge_storage(
+      &mut self,
+      storage_used: u64,
+      payer_id: &AccountId,
+      refund_id: AccountId,
+    ) {
+      let required_cost_to_store_info = env::storage_byte_cost() 
+          * Balance::from(storage_used);
 
+      let attached_deposit = env::attached_deposit();
 
-/// refund the initial deposit based on the amount of 
-/// storage that was used up
-pub(crate) fn refund_deposit(storage_used: u64, to_signer: AccountId) {
-    let required_cost_to_store_info = env::storage_byte_cost() 
-        * Balance::from(storage_used);  // move up if fail. 
+      if required_cost_to_store_info <= attached_deposit {
+        let refund = attached_deposit - required_cost_to_store_info;
 
-    let attached_deposit = env::attached_deposit();
+        // if refund is greater than 1 yoctoNEAR, refund that amount. 
+        if refund > 1 {
+          Promise::new(refund_id).transfer(refund);
+        }
+        return;
+      }
 
-    require!(  // use assert or if and env::panic if fail. 
-      required_cost_to_store_info <= attached_deposit,
-      format!("Must attach {} yoctoNEAR to cover storage",
-        required_cost_to_store_info),
-    );
+      let shortfall = required_cost_to_store_info - attached_deposit;
+      let balance = self.storage_deposits.get(payer_id).unwrap_or(0);
 
-    let refund = attached_deposit - required_cost_to_store_info;
+      require!(
+        shortfall <= self.internal_storage_available(payer_id),
+        format!(
+          "Must attach {} yoctoNEAR or have it in storage balance to cover storage",
+          shortfall
+        ),
+      );
 
-    // if refund is greater than 1 yoctoNEAR, 
-    // refund the predecessor that amount. 
-    if refund > 1 {
-        Promise::new(to_signer).transfer(refund);
+      self.storage_deposits.insert(payer_id, &(balance - shortfall));
     }
-}
-
 
-pub(crate) fn refund_storage_freed(storage_freed: u64, to_signer: AccountId) {
-    let required_cost_to_store_info = env::storage_byte_cost() 
-        * Balance::from(storage_freed);  // move up if fail. 
+    /// Credit storage freed back to an account's storage balance. 
+    pub(crate) fn internal_credit_storage(&mut self, storage_freed: u64, account_id: &AccountId) {
+      let balance = self.storage_deposits.get(account_id).unwrap_or(0);
 
-    let attached_deposit = env::attached_deposit();
-
-    require!(  // use assert or if and env::panic if fail. 
-      required_cost_to_store_info <= attached_deposit,
-      format!("Must attach {} yoctoNEAR to cover storage",
-        required_cost_to_store_info),
-    );
-
-    let refund = attached_deposit + required_cost_to_store_info;
-
-    if refund > 1 {
-      Promise::new(to_signer).transfer(refund);
+      self.storage_deposits.insert(
+        account_id,
+        &(balance + Balance::from(storage_freed) * env::storage_byte_cost())
+      );
     }
-}
-
 
-/// Assert user has attached at least 1 yoctoNEAr (for security reasons
-/// and payment of storage)
-pub(crate) fn assert_at_least_one_yocto() {
-  require!(
-    env::attached_deposit() >= 1,
-    "Requires attached deposit of at least 1 yoctoNEAR",
-  )
-}
+    /// credit storage taken up by approved account IDs back to the storage
+    /// balance of the passed-in account ID. 
+    pub(crate) fn refund_approved_account_ids_iter<'a, I>(
+      &mut self,
+      account_id: AccountId,
+      approved_account_ids: I,  // approved account IDs must be passed in as iterator
+    )
+    where
+      I: Iterator<Item = &'a AccountId>,
+    {
+      // sum of all bytes for each approved account IDs. 
+      let storage_released: u64 = approved_account_ids.map(bytes_for_approved_account_id).sum();
+      self.internal_credit_storage(storage_released, &account_id);
+    }
 
+    /// credit storage taken up by a map of approved account IDs back to the
+    /// storage balance of the passed-in account ID. 
+    pub(crate) fn refund_approved_account_ids(
+      &mut self,
+      account_id: AccountId,
+      approved_account_ids: &HashMap<AccountId, u64>,
+    ) {
+      self.refund_approved_account_ids_iter(account_id, approved_account_ids.keys())
+    }
 
-impl Contract {
     /// add a token to the set of tokens an owner has. 
     pub(crate) fn internal_add_token_to_owner(
       &mut self,
@@ -181,6 +178,46 @@ impl Contract {
       token_id: &TokenId,
       approval_id: Option<u64>,  // approved ID can transfer token. 
       memo: Option<String>,
+    ) -> Token {
+      let token = self.internal_move_token(sender_id, receiver_id, token_id, approval_id);
+
+      // log memo if available
+      if let Some(memo) = memo.as_ref() {
+        env::log_str(&format!("Memo: {}", memo).to_string());
+      }
+
+      let mut authorized_id = None;
+      if approval_id.is_some() {
+        authorized_id = Some(sender_id.to_string());
+      }
+
+      let nft_transfer_log: EventLog = EventLog {
+        standard: NFT_STANDARD_NAME.to_string(),
+        version : NFT_METADATA_SPEC.to_string(),
+        event   : EventLogVariant::NftTransfer(vec![NftTransferLog {
+          authorized_id,
+          old_owner_id: token.owner_id.to_string(),
+          new_owner_id: receiver_id.to_string(),
+          token_ids   : vec![token_id.to_string()],
+          memo,
+        }]),
+      };
+
+      // log serialized json
+      env::log_str(&nft_transfer_log.to_string());
+
+      // return previous token object that was transferred. 
+      token
+    }
+
+    /// move the NFT to the receiver_id without logging the transfer event.
+    /// Returns previous token object. 
+    pub(crate) fn internal_move_token(
+      &mut self,
+      sender_id: &AccountId,
+      receiver_id: &AccountId,
+      token_id: &TokenId,
+      approval_id: Option<u64>,
     ) -> Token {
       let token = self.tokens_by_id.get(token_id).expect("No token");
 
@@ -209,6 +246,11 @@ impl Contract {
         }
       }
 
+      // tickets that are redeemed or about to start cannot change hands. 
+      if let Some(reason) = self.internal_transfer_lock_reason(token_id) {
+        env::panic_str(&reason);
+      }
+
       // make sure sender isn't sending token to themselves. 
       require!(
         &token.owner_id != receiver_id,
@@ -232,33 +274,73 @@ impl Contract {
 
       self.tokens_by_id.insert(token_id, &new_token);  // replace old entry
 
-      // log memo if available
-      if let Some(memo) = memo.as_ref() {
-        env::log_str(&format!("Memo: {}", memo).to_string());
+      token
+    }
+
+    /// remove a token and everything stored for it. Returns the burnt token. 
+    pub(crate) fn internal_burn(&mut self, token_id: &TokenId) -> Token {
+      let token = self.tokens_by_id.remove(token_id).expect("No token");
+
+      self.internal_remove_token_from_owner(&token.owner_id, token_id);
+
+      // shared owners also display the token in their wallet. 
+      if let Some(share_accounts) = self.share_nfts.remove(token_id) {
+        let share_accounts: HashSet<AccountId> = share_accounts.into_iter().collect();
+
+        for account_id in share_accounts.iter() {
+          if account_id != &token.owner_id {
+            self.internal_remove_token_from_owner(account_id, token_id);
+          }
+        }
       }
 
-      let mut authorized_id = None;
-      if approval_id.is_some() {
-        authorized_id = Some(sender_id.to_string());
+      self.token_metadata_by_id.remove(token_id);
+      self.ticket_used.remove(token_id);
+      self.transfer_rules.remove(token_id);
+      self.purchase_price.remove(token_id);
+      self.minted_by.remove(token_id);
+
+      token
+    }
+
+    /// Check the token's transfer rules. Returns the reason if the token 
+    /// currently cannot be transferred, None otherwise. 
+    pub(crate) fn internal_transfer_lock_reason(&self, token_id: &TokenId) -> Option<String> {
+      let transfer_rules = self.transfer_rules.get(token_id)?;
+
+      if transfer_rules.lock_after_use {
+        let ticket_used = self.ticket_used.get(token_id).unwrap_or_default();
+
+        if ticket_used.iter().any(|used| *used) {
+          return Some("Ticket has been used and can no longer be transferred.".to_string());
+        }
       }
 
-      let nft_transfer_log: EventLog = EventLog {
-        standard: NFT_STANDARD_NAME.to_string(),
-        version : NFT_METADATA_SPEC.to_string(),
-        event   : EventLogVariant::NftTransfer(vec![NftTransferLog {
-          authorized_id,
-          old_owner_id: token.owner_id.to_string(),
-          new_owner_id: receiver_id.to_string(),
-          token_ids   : vec![token_id.to_string()],
-          memo,
-        }]),
-      };
+      if let Some(lock_before_start) = transfer_rules.lock_before_start {
+        let starts_at = self.token_metadata_by_id.get(token_id)
+            .and_then(|metadata| metadata.starts_at);
+
+        if let Some(starts_at) = starts_at {
+          if block_timestamp_ms() + lock_before_start >= starts_at {
+            return Some(format!(
+              "Transfers are locked from {} ms before the event starts at {}.",
+              lock_before_start,
+              starts_at
+            ));
+          }
+        }
+      }
 
-      // log serialized json
-      env::log_str(&nft_transfer_log.to_string());
+      None
+    }
 
-      // return previous token object that was transferred. 
-      token
+    /// Maximum price the token can be resold for, if the token has both a
+    /// recorded purchase price and a resale cap. 
+    pub(crate) fn internal_resale_cap(&self, token_id: &TokenId) -> Option<Balance> {
+      let max_resale_bps = self.transfer_rules.get(token_id)?.max_resale_bps?;
+      let purchase_price = self.purchase_price.get(token_id)?;
+
+      Some(purchase_price * max_resale_bps as u128 / 10_000u128)
     }
 
 
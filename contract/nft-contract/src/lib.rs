@@ -8,9 +8,7 @@ use near_sdk::{
     PanicOnDefault, Promise, PromiseOrValue, assert_one_yocto
 };
 
-use near_helper::{
-  expect_lightweight, near_to_yoctonear
-};
+use near_helper::expect_lightweight;
 
 use std::collections::HashSet;
 
@@ -23,13 +21,17 @@ pub use crate::royalty::*;
 pub use crate::events::*;
 
 mod approval; 
+mod burn;
 mod enumeration; 
+mod estimate;
 mod internal;
 mod metadata; 
 mod mint; 
 mod nft_core; 
 mod royalty; 
+mod storage;
 mod events;
+mod ticket;
 
 pub const NFT_METADATA_SPEC: &str = "1.0.0";
 pub const NFT_STANDARD_NAME: &str = "nep171";
@@ -57,6 +59,19 @@ pub struct Contract {
 
     // A boolean where "tick" means used ticket, "false" means unused ticket. 
     pub ticket_used: LookupMap<TokenId, Vec<bool>>,
+
+    // Transfer lockout rules for a given token ID, if any. 
+    pub transfer_rules: LookupMap<TokenId, TransferRules>,
+
+    // Original purchase price (face value) of a token, recorded at mint. 
+    pub purchase_price: LookupMap<TokenId, Balance>,
+
+    // Account that minted a given token ID, allowed to burn it (e.g. for refunds). 
+    pub minted_by: LookupMap<TokenId, AccountId>,
+
+    // Prepaid storage balance per account, drawn from when storage is used
+    // and credited when it's freed. 
+    pub storage_deposits: LookupMap<AccountId, Balance>,
 }
 
 /// Helper structure for keys of the persistent collections.
@@ -72,6 +87,10 @@ pub enum StorageKey {
     TokenTypesLocked,
     ShareNFTs,
     TicketUsed,
+    TransferRules,
+    PurchasePrice,
+    MintedBy,
+    StorageDeposits,
 }
 
 #[near_bindgen]
@@ -126,6 +145,10 @@ impl Contract {
 
           share_nfts: LookupMap::new(StorageKey::ShareNFTs.try_to_vec().unwrap()),
           ticket_used: LookupMap::new(StorageKey::TicketUsed.try_to_vec().unwrap()),
+          transfer_rules: LookupMap::new(StorageKey::TransferRules.try_to_vec().unwrap()),
+          purchase_price: LookupMap::new(StorageKey::PurchasePrice.try_to_vec().unwrap()),
+          minted_by: LookupMap::new(StorageKey::MintedBy.try_to_vec().unwrap()),
+          storage_deposits: LookupMap::new(StorageKey::StorageDeposits.try_to_vec().unwrap()),
         };
 
         // return the contract object
@@ -52,6 +52,24 @@ pub struct Token {
     pub royalty: HashMap<AccountId, u16>,
 }
 
+/// Rules restricting when a ticket may change hands. Set per template
+/// by the marketplace (or by whoever mints) and enforced on every transfer. 
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TransferRules {
+    pub lock_after_use: bool,  // no transfer once any seat in `ticket_used` is redeemed. 
+    pub lock_before_start: Option<u64>,  // milliseconds before `starts_at` where transfer is locked. 
+    pub max_resale_bps: Option<u16>,  // max resale price in basis points of purchase price, e.g. 11000 = 110%. 
+}
+
+/// Returned from `nft_is_transferable` view call. 
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Transferability {
+    pub transferable: bool,
+    pub reason: Option<String>,  // why not, if not transferable. 
+}
+
 //The Json token is what will be returned from view calls. 
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
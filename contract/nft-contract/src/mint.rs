@@ -1,5 +1,21 @@
 use crate::*;
-use near_sdk::require;
+use near_sdk::{require, Gas};
+
+// gas needed to mint one token in nft_batch_mint, which caps the batch size. 
+const GAS_PER_BATCH_MINT: Gas = Gas(5_000_000_000_000);
+
+/// One token to mint in nft_batch_mint. 
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MintArgs {
+    pub token_id: TokenId,
+    pub metadata: TokenMetadata,
+    pub receiver_id: AccountId,
+    pub perpetual_royalties: Option<HashMap<AccountId, u16>>,
+    pub size: Option<usize>,
+    pub transfer_rules: Option<TransferRules>,
+    pub purchase_price: Option<U128>,
+}
 
 #[near_bindgen]
 impl Contract {
@@ -12,10 +28,127 @@ impl Contract {
         perpetual_royalties: Option<HashMap<AccountId, u16>>,
         size: Option<usize>,
         refund_to_signer: Option<AccountId>,
+        transfer_rules: Option<TransferRules>,
+        purchase_price: Option<U128>,
+        memo: Option<String>,  // e.g. gift message, logged in the mint event. 
     ) {
       // measure the initial storage being used on contract.
         let initial_storage_usage = env::storage_usage();
 
+        self.internal_mint(MintArgs {
+          token_id: token_id.clone(),
+          metadata,
+          receiver_id: receiver_id.clone(),
+          perpetual_royalties,
+          size,
+          transfer_rules,
+          purchase_price,
+        });
+
+        // Log the minting as per events standard. 
+        let nft_mint_log: EventLog = EventLog {
+          standard: NFT_STANDARD_NAME.to_string(),
+          version : NFT_METADATA_SPEC.to_string(),
+          event   : EventLogVariant::NftMint(vec![NftMintLog {
+            owner_id : receiver_id.to_string(),
+            token_ids: vec![token_id.to_string()],
+            memo,  // optional
+          }]),
+        };
+
+        // log serialized json
+        env::log_str(&nft_mint_log.to_string());
+
+        // calculate required storage
+        let required_storage_in_bytes = env::storage_usage() - initial_storage_usage;
+
+        // refund excess storage if user attached too much. If they didn't 
+        // attach enough, take the rest from the caller's storage balance, or 
+        // panic. refund_to_signer only ever receives refunds. 
+        let refund_id = refund_to_signer.unwrap_or_else(env::predecessor_account_id);
+        self.internal_charge_storage(
+          required_storage_in_bytes, &env::predecessor_account_id(), refund_id
+        );
+    }
+
+    /// Mint many tokens in one call, paying storage for all of them at once.
+    /// Logs a single mint event. How many tokens can be minted is limited
+    /// by the gas attached. 
+    #[payable]
+    pub fn nft_batch_mint(
+        &mut self,
+        tokens: Vec<MintArgs>,
+        refund_to_signer: Option<AccountId>,
+        memo: Option<String>,
+    ) -> Vec<TokenId> {
+        let max_tokens = env::prepaid_gas().0 / GAS_PER_BATCH_MINT.0;
+
+        require!(
+          !tokens.is_empty() && tokens.len() as u64 <= max_tokens,
+          format!(
+            "Can mint between 1 and {} tokens with the gas attached, {} per token.",
+            max_tokens,
+            GAS_PER_BATCH_MINT.0
+          )
+        );
+
+        let initial_storage_usage = env::storage_usage();
+
+        // one log per owner, in the order owners first appear. 
+        let mut mint_logs: Vec<NftMintLog> = Vec::new();
+        let mut token_ids: Vec<TokenId> = Vec::new();
+
+        for mint_args in tokens {
+          let owner_id = mint_args.receiver_id.to_string();
+          let token_id = mint_args.token_id.clone();
+
+          self.internal_mint(mint_args);
+
+          match mint_logs.iter_mut().find(|mint_log| mint_log.owner_id == owner_id) {
+            Some(mint_log) => mint_log.token_ids.push(token_id.clone()),
+            None => mint_logs.push(NftMintLog {
+              owner_id,
+              token_ids: vec![token_id.clone()],
+              memo: memo.clone(),
+            }),
+          }
+
+          token_ids.push(token_id);
+        }
+
+        let nft_mint_log: EventLog = EventLog {
+          standard: NFT_STANDARD_NAME.to_string(),
+          version : NFT_METADATA_SPEC.to_string(),
+          event   : EventLogVariant::NftMint(mint_logs),
+        };
+
+        env::log_str(&nft_mint_log.to_string());
+
+        let required_storage_in_bytes = env::storage_usage() - initial_storage_usage;
+
+        let refund_id = refund_to_signer.unwrap_or_else(env::predecessor_account_id);
+        self.internal_charge_storage(
+          required_storage_in_bytes, &env::predecessor_account_id(), refund_id
+        );
+
+        token_ids
+    }
+}
+
+
+impl Contract {
+    /// Store a new token and everything recorded for it. 
+    pub(crate) fn internal_mint(&mut self, mint_args: MintArgs) {
+        let MintArgs {
+          token_id,
+          metadata,
+          receiver_id,
+          perpetual_royalties,
+          size,
+          transfer_rules,
+          purchase_price,
+        } = mint_args;
+
         // create royalty map to store the token.
         let mut royalty = HashMap::new();
 
@@ -59,32 +192,15 @@ impl Contract {
           self.ticket_used.insert(&token_id, &vec![false; 1]);  // being explicit about size.
         }
 
-        // ===========================================================
-
-        // Log the minting as per events standard. 
-        let nft_mint_log: EventLog = EventLog {
-          standard: NFT_STANDARD_NAME.to_string(),
-          version : NFT_METADATA_SPEC.to_string(),
-          event   : EventLogVariant::NftMint(vec![NftMintLog {
-            owner_id : token.owner_id.to_string(),
-            token_ids: vec![token_id.to_string()],
-            memo     : None,  // optional
-          }]),
-        };
-
-        // log serialized json
-        env::log_str(&nft_mint_log.to_string());
+        if let Some(transfer_rules) = transfer_rules {
+          self.transfer_rules.insert(&token_id, &transfer_rules);
+        }
 
-        // calculate required storage
-        let required_storage_in_bytes = env::storage_usage() - initial_storage_usage;
+        self.minted_by.insert(&token_id, &env::predecessor_account_id());
 
-        // refund excess storage if user attached too much. 
-        // Panic if they didn't attach enough. 
-        if let Some(refund_to_signer) = refund_to_signer {
-          refund_deposit(required_storage_in_bytes, refund_to_signer);
-        } else {
-          refund_deposit(required_storage_in_bytes, env::predecessor_account_id());
+        if let Some(purchase_price) = purchase_price {
+          self.purchase_price.insert(&token_id, &purchase_price.0);
         }
-        
+        // ===========================================================
     }
-}
\ No newline at end of file
+}
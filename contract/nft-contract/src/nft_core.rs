@@ -6,6 +6,9 @@ const GAS_FOR_NFT_TRANSFER_CALL: Gas = Gas(25_000_000_000_000 + GAS_FOR_RESOLVE_
 const MIN_GAS_FOR_NFT_TRANSFER_CALL: Gas = Gas(100_000_000_000_000);
 const NO_DEPOSIT: Balance = 0;
 
+// gas needed to transfer one token in nft_batch_transfer, which caps the batch size. 
+const GAS_PER_BATCH_TRANSFER: Gas = Gas(5_000_000_000_000);
+
 pub trait NonFungibleTokenCore {
     /// set other accounts
     fn set_accounts(
@@ -91,7 +94,7 @@ trait NonFungibleTokenResolver {
 impl NonFungibleTokenCore for Contract {
 
     /// Set accounts. 
-    /// Payable to pay for storage; but limit how much deposit user can attach. 
+    /// Payable to pay for storage; what isn't attached comes from storage balance. 
     /// Dang... this is REALLY REALLY MESSY as it's a "rubbish draft." I hate this
     /// quite.... if we're to continue on, this requires REFACTORING!!!
     #[payable]
@@ -101,11 +104,6 @@ impl NonFungibleTokenCore for Contract {
       share_accounts: Vec<AccountId>,
       refund_to_signer: Option<AccountId>,
     ) {
-      require!(
-        env::attached_deposit() >= near_to_yoctonear(0.1),
-        "Attached too little money. Min attach: 0.1 NEAR."
-      );
-
       let owner_id = env::predecessor_account_id();
 
       let token = expect_lightweight(
@@ -132,30 +130,8 @@ impl NonFungibleTokenCore for Contract {
         )
       );
 
-      let mut total_storage = 0i64;
-
-      // If we were to implement iterator like this: 
-      // approved_account_ids.map(bytes_for_approved_account_id).sum();
-      // it needs the iterator trait; One is too lazy to fight the compiler,
-      // so we'll use for loops. 
-
-      // Old used bytes
-      let mut old_bytes = 0;
-      for account_id in &old_share_accounts {
-        old_bytes += bytes_for_approved_account_id(account_id);
-      }
-      
-      // New going to use bytes
-      let mut new_bytes = 0;
-      for account_id in &share_accounts {
-        new_bytes += bytes_for_approved_account_id(account_id);
-      }
-
-      total_storage += new_bytes as i64 - old_bytes as i64;
-      
-
-      // we don't need the refund above; can just use this at the beginning lol... 
-      let partial_storage_usage = env::storage_usage();
+      // only the storage actually used or freed is charged or credited. 
+      let initial_storage_usage = env::storage_usage();
       
       // Change the values after sorting out the storage
       self.share_nfts.insert(&token_id, &share_accounts);
@@ -178,8 +154,7 @@ impl NonFungibleTokenCore for Contract {
         }
       }
 
-      let current_storage = env::storage_usage();
-      total_storage += current_storage as i64 - partial_storage_usage as i64;
+      let total_storage = env::storage_usage() as i64 - initial_storage_usage as i64;
 
       let refund_target = match refund_to_signer {
         Some(value) => value, 
@@ -188,11 +163,11 @@ impl NonFungibleTokenCore for Contract {
 
       if total_storage < 0 {
         // new storage usage is less than before. 
-        refund_storage_freed(total_storage.abs() as u64, refund_target);
-      } else if total_storage > 0 {
-        refund_deposit(total_storage as u64, refund_target);
+        self.internal_credit_storage(total_storage.unsigned_abs(), &owner_id);
+        self.internal_charge_storage(0, &owner_id, refund_target);
       } else {
-        Promise::new(refund_target).transfer(env::attached_deposit());
+        // if attached not enough, take the rest from storage balance. 
+        self.internal_charge_storage(total_storage as u64, &owner_id, refund_target);
       }
 
       // Ok, I think that's done? Anything I missed out? 
@@ -224,8 +199,8 @@ impl NonFungibleTokenCore for Contract {
           memo,
         );
 
-        // refund owner for releasing the used up storage by approved account IDs.
-        refund_approved_account_ids(
+        // credit owner storage balance for the storage released by approved account IDs.
+        self.refund_approved_account_ids(
           previous_token.owner_id.clone(),
           &previous_token.approved_account_ids,
         );
@@ -353,7 +328,7 @@ impl NonFungibleTokenResolver for Contract {
               // we don't have to revert the original transfer, thus we can just return
               // true since nothing went wrong. We refund the owner for releasing the
               // storage used up by the approved account IDs. 
-              refund_approved_account_ids(owner_id, &approved_account_ids);
+              self.refund_approved_account_ids(owner_id, &approved_account_ids);
               return true;
             }
           }
@@ -362,12 +337,12 @@ impl NonFungibleTokenResolver for Contract {
         // get token object if got some token object
         let mut token = if let Some(token) = self.tokens_by_id.get(&token_id) {
           if token.owner_id != receiver_id {  // receiver_id is the receiver. 
-            refund_approved_account_ids(owner_id, &approved_account_ids);
+            self.refund_approved_account_ids(owner_id, &approved_account_ids);
             return true;  
           }
           token
         } else {  // no token object, it was burned. 
-          refund_approved_account_ids(owner_id, &approved_account_ids);
+          self.refund_approved_account_ids(owner_id, &approved_account_ids);
           return true;
         };
 
@@ -385,7 +360,7 @@ impl NonFungibleTokenResolver for Contract {
         token.owner_id = owner_id.clone();
 
         // refund approved account IDs may have set on token. 
-        refund_approved_account_ids(receiver_id.clone(), &token.approved_account_ids);
+        self.refund_approved_account_ids(receiver_id.clone(), &token.approved_account_ids);
 
         // we insert the token back into the tokens_by_id collection
         self.tokens_by_id.insert(&token_id, &token);
@@ -411,4 +386,84 @@ impl NonFungibleTokenResolver for Contract {
         // receiver_id didn't successfully receive the token. 
         false
     }
-}
\ No newline at end of file
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Transfer many tokens in one call, each `(token_id, receiver_id, approval_id)`.
+    /// Storage released by approvals is credited to each previous owner's storage balance,
+    /// and transfers are logged grouped by old owner and receiver. How many
+    /// tokens can be transferred is limited by the gas attached. 
+    #[payable]
+    pub fn nft_batch_transfer(
+        &mut self,
+        transfers: Vec<(TokenId, AccountId, Option<u64>)>,
+        memo: Option<String>,
+    ) {
+        assert_one_yocto();
+
+        let max_transfers = env::prepaid_gas().0 / GAS_PER_BATCH_TRANSFER.0;
+
+        require!(
+          !transfers.is_empty() && transfers.len() as u64 <= max_transfers,
+          format!(
+            "Can transfer between 1 and {} tokens with the gas attached, {} per token.",
+            max_transfers,
+            GAS_PER_BATCH_TRANSFER.0
+          )
+        );
+
+        let sender_id = env::predecessor_account_id();
+
+        // bytes released by approvals, per previous owner. 
+        let mut storage_released: HashMap<AccountId, u64> = HashMap::new();
+        let mut transfer_logs: Vec<NftTransferLog> = Vec::new();
+
+        for (token_id, receiver_id, approval_id) in transfers {
+          let previous_token = self.internal_move_token(
+            &sender_id,
+            &receiver_id,
+            &token_id,
+            approval_id,
+          );
+
+          let approvals_bytes: u64 = previous_token.approved_account_ids.keys()
+              .map(bytes_for_approved_account_id)
+              .sum();
+          *storage_released.entry(previous_token.owner_id.clone()).or_insert(0) += approvals_bytes;
+
+          let authorized_id = approval_id.map(|_| sender_id.to_string());
+          let old_owner_id = previous_token.owner_id.to_string();
+          let new_owner_id = receiver_id.to_string();
+
+          match transfer_logs.iter_mut().find(|transfer_log| 
+            transfer_log.authorized_id == authorized_id
+              && transfer_log.old_owner_id == old_owner_id
+              && transfer_log.new_owner_id == new_owner_id
+          ) {
+            Some(transfer_log) => transfer_log.token_ids.push(token_id),
+            None => transfer_logs.push(NftTransferLog {
+              authorized_id,
+              old_owner_id,
+              new_owner_id,
+              token_ids: vec![token_id],
+              memo: memo.clone(),
+            }),
+          }
+        }
+
+        for (owner_id, bytes) in storage_released {
+          if bytes > 0 {
+            self.internal_credit_storage(bytes, &owner_id);
+          }
+        }
+
+        let nft_transfer_log: EventLog = EventLog {
+          standard: NFT_STANDARD_NAME.to_string(),
+          version : NFT_METADATA_SPEC.to_string(),
+          event   : EventLogVariant::NftTransfer(transfer_logs),
+        };
+
+        env::log_str(&nft_transfer_log.to_string());
+    }
+}
@@ -72,6 +72,18 @@ impl NonFungibleTokenCore for Contract {
         max_len_payout: u16,
     ) -> Payout {
         assert_one_yocto();
+
+        // anti-scalping: refuse resale above the token's cap. 
+        if let Some(resale_cap) = self.internal_resale_cap(&token_id) {
+          require!(
+            balance.0 <= resale_cap,
+            format!(
+              "Resale price {} exceeds the maximum allowed {} for this token.",
+              balance.0,
+              resale_cap
+            )
+          );
+        }
         
         let sender_id = env::predecessor_account_id();
 
@@ -86,7 +98,7 @@ impl NonFungibleTokenCore for Contract {
 
         // refund previous token owner for storage used up by previous approved
         // account IDs
-        refund_approved_account_ids(
+        self.refund_approved_account_ids(
           previous_token.owner_id.clone(),
           &previous_token.approved_account_ids,
         );
@@ -0,0 +1,148 @@
+use crate::*;
+use near_sdk::require;
+
+// Storage management (NEP-145 style). Accounts prepay a storage balance that
+// minting, approvals and set_accounts draw from, and burns credit back to.
+// The min balance is locked while registered, to pay for the account's own
+// entry; the rest is available. 
+
+// bytes of one storage_deposits entry for the longest account ID: 1 byte
+// prefix, borsh string length and 64 char account ID as key, u128 balance,
+// and 40 bytes the runtime charges per record. 
+const STORAGE_BYTES_PER_ACCOUNT: u64 = 1 + 4 + 64 + 16 + 40;
+
+/// Balance locked while an account is registered. 
+fn storage_balance_min() -> Balance {
+  Balance::from(STORAGE_BYTES_PER_ACCOUNT) * env::storage_byte_cost()
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalance {
+    pub total: U128,
+    pub available: U128,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalanceBounds {
+    pub min: U128,
+    pub max: Option<U128>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Deposit to the storage balance of account_id, or the caller if None.
+    /// Registering takes at least the min balance. With registration_only,
+    /// deposit above the min is refunded, or all of it if already registered.
+    #[payable]
+    pub fn storage_deposit(
+      &mut self,
+      account_id: Option<AccountId>,
+      registration_only: Option<bool>,
+    ) -> StorageBalance {
+      let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+      let deposit = env::attached_deposit();
+      let registration_only = registration_only.unwrap_or(false);
+
+      let (balance, refund) = match self.storage_deposits.get(&account_id) {
+        Some(balance) if registration_only => (balance, deposit),
+        Some(balance) => (balance + deposit, 0),
+        None => {
+          let min = storage_balance_min();
+          require!(
+            deposit >= min,
+            format!("Must attach at least {} yoctoNEAR to register.", min)
+          );
+
+          if registration_only { (min, deposit - min) } else { (deposit, 0) }
+        }
+      };
+
+      self.storage_deposits.insert(&account_id, &balance);
+
+      if refund > 0 {
+        Promise::new(env::predecessor_account_id()).transfer(refund);
+      }
+
+      self.storage_balance_of(account_id).unwrap()
+    }
+
+    /// Withdraw from the caller's available storage balance, all of it if
+    /// no amount. 
+    #[payable]
+    pub fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+      assert_one_yocto();
+
+      let account_id = env::predecessor_account_id();
+      let balance = expect_lightweight(
+        self.storage_deposits.get(&account_id),
+        "Account is not registered."
+      );
+
+      let available = self.internal_storage_available(&account_id);
+      let amount = amount.map(|amount| amount.0).unwrap_or(available);
+
+      require!(
+        amount <= available,
+        format!("Cannot withdraw {}, available storage balance is only {}.", amount, available)
+      );
+
+      self.storage_deposits.insert(&account_id, &(balance - amount));
+
+      if amount > 0 {
+        Promise::new(account_id.clone()).transfer(amount);
+      }
+
+      self.storage_balance_of(account_id).unwrap()
+    }
+
+    /// Remove the caller's storage account and refund its whole balance,
+    /// min included. Tokens owned keep their (already paid) storage. Returns
+    /// false if the account wasn't registered. 
+    #[payable]
+    pub fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+      assert_one_yocto();
+
+      // only the min is locked, for the entry removed here, so force makes
+      // no difference. 
+      let _ = force;
+
+      let account_id = env::predecessor_account_id();
+
+      match self.storage_deposits.remove(&account_id) {
+        Some(balance) => {
+          if balance > 0 {
+            Promise::new(account_id).transfer(balance);
+          }
+          true
+        },
+        None => false
+      }
+    }
+
+    // views
+    /// min is the cost of the account's own entry, and no maximum. 
+    pub fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+      StorageBalanceBounds {
+        min: U128(storage_balance_min()),
+        max: None,
+      }
+    }
+
+    /// storage balance of an account. None if not registered. 
+    pub fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+      self.storage_deposits.get(&account_id).map(|balance| StorageBalance {
+        total: U128(balance),
+        available: U128(self.internal_storage_available(&account_id)),
+      })
+    }
+}
+
+
+impl Contract {
+    /// Storage balance an account can spend or withdraw: all but the min. 
+    pub(crate) fn internal_storage_available(&self, account_id: &AccountId) -> Balance {
+      self.storage_deposits.get(account_id).unwrap_or(0).saturating_sub(storage_balance_min())
+    }
+}
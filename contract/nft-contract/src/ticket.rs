@@ -0,0 +1,54 @@
+use crate::*;
+use near_sdk::require;
+
+#[near_bindgen]
+impl Contract {
+    /// Redeem a seat of a ticket (e.g. at the zoo gate). Only the contract
+    /// owner can mark tickets as used. 
+    #[payable]
+    pub fn use_ticket(&mut self, token_id: TokenId, seat: usize) {
+      assert_one_yocto();
+
+      require!(
+        env::predecessor_account_id() == self.owner_id,
+        "Only contract owner can mark tickets as used."
+      );
+
+      let mut ticket_used = expect_lightweight(
+        self.ticket_used.get(&token_id),
+        "No token"
+      );
+
+      require!(
+        seat < ticket_used.len(),
+        format!("Seat {} out of range, ticket has {} seats.", seat, ticket_used.len())
+      );
+
+      require!(!ticket_used[seat], "Seat already used.");
+
+      ticket_used[seat] = true;
+      self.ticket_used.insert(&token_id, &ticket_used);
+    }
+
+    /// Whether a token can currently be transferred, and why not if it can't. 
+    pub fn nft_is_transferable(&self, token_id: TokenId) -> Transferability {
+      require!(self.tokens_by_id.contains_key(&token_id), "No token");
+
+      let reason = self.internal_transfer_lock_reason(&token_id);
+
+      Transferability {
+        transferable: reason.is_none(),
+        reason,
+      }
+    }
+
+    /// view transfer rules of a token, if any. 
+    pub fn nft_transfer_rules(&self, token_id: TokenId) -> Option<TransferRules> {
+      self.transfer_rules.get(&token_id)
+    }
+
+    /// view the maximum resale price of a token, if capped. 
+    pub fn nft_resale_cap(&self, token_id: TokenId) -> Option<U128> {
+      self.internal_resale_cap(&token_id).map(U128)
+    }
+}